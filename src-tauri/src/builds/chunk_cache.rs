@@ -0,0 +1,453 @@
+use serde::Serialize;
+use sha2::{ Digest, Sha256 };
+use std::collections::HashMap;
+use std::fs;
+use std::io::{ self, Read, Seek, SeekFrom, Write };
+use std::path::{ Path, PathBuf };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+use super::file_name_for;
+
+const CHUNK_EXTENSION: &str = "chunk";
+const DEFAULT_QUOTA_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+#[derive(Serialize)]
+pub struct ChunkCacheInfo {
+    pub path: String,
+    pub entries: usize,
+    pub size_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+/// Identifies a decompressed chunk. Chunks with a known hash are stored by content so every
+/// version that ships the same data shares one entry, the others by version and chunk ID.
+pub struct ChunkKey {
+    name: String,
+    hash: Option<String>,
+    /// Decompressed size, when the manifest gives one.
+    size: Option<u64>,
+}
+
+impl ChunkKey {
+    pub fn new(version: &str, chunk_id: i32, hash: Option<&str>, size: Option<u64>) -> Self {
+        match hash {
+            Some(hash) => {
+                let hash = hash.to_ascii_lowercase();
+                Self {
+                    name: format!("sha256-{}", file_name_for(&hash)),
+                    hash: Some(hash),
+                    size,
+                }
+            }
+            None =>
+                Self {
+                    name: format!("{}-{}", file_name_for(version), chunk_id),
+                    hash: None,
+                    size,
+                },
+        }
+    }
+}
+
+struct CacheEntry {
+    size: u64,
+    last_used: SystemTime,
+}
+
+struct CacheState {
+    quota_bytes: u64,
+    total_bytes: u64,
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// Decompressed chunks kept on disk between installs, bounded by a size quota. When the
+/// quota is exceeded the least recently used chunks are evicted first.
+pub struct ChunkCache {
+    dir: PathBuf,
+    state: Mutex<CacheState>,
+    temp_counter: AtomicU64,
+}
+
+impl Default for ChunkCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkCache {
+    pub fn new() -> Self {
+        let dir = dirs
+            ::data_local_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("com.solarisfn.org")
+            .join("chunks");
+
+        Self::with_dir(dir)
+    }
+
+    /// Opens the cache in `dir`, indexing whatever chunks an earlier session left there.
+    /// File modification times stand in for the last use of each chunk.
+    pub fn with_dir(dir: PathBuf) -> Self {
+        let mut entries = HashMap::new();
+        let mut total_bytes = 0;
+
+        if let Ok(read_dir) = fs::read_dir(&dir) {
+            for entry in read_dir.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                if path.extension().is_none_or(|ext| ext != CHUNK_EXTENSION) {
+                    // Leftover from a write that never finished
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+
+                let (Some(name), Ok(metadata)) = (path.file_stem(), entry.metadata()) else {
+                    continue;
+                };
+
+                total_bytes += metadata.len();
+                entries.insert(name.to_string_lossy().to_string(), CacheEntry {
+                    size: metadata.len(),
+                    last_used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                });
+            }
+        }
+
+        Self {
+            dir,
+            state: Mutex::new(CacheState {
+                quota_bytes: DEFAULT_QUOTA_BYTES,
+                total_bytes,
+                entries,
+            }),
+            temp_counter: AtomicU64::new(0),
+        }
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, CHUNK_EXTENSION))
+    }
+
+    pub async fn contains(&self, key: &ChunkKey) -> bool {
+        self.state.lock().await.entries.contains_key(&key.name)
+    }
+
+    /// Copies the chunk stored under `key` into `dest` at `offset` and returns its size, or
+    /// `None` when the cache holds no usable copy. Nothing past the chunk's own range is
+    /// written, so a damaged entry cannot overwrite the chunks after it.
+    pub async fn get(&self, key: &ChunkKey, dest: &Path, offset: u64) -> Option<u64> {
+        let indexed_size = self.state.lock().await.entries.get(&key.name)?.size;
+
+        // Entries are checked on the way out so a truncated or damaged cache file is
+        // downloaded again instead of ending up in an install. The size is known before
+        // copying, the hash only after, when a mismatch is overwritten by the download.
+        let path = self.entry_path(&key.name);
+        let damaged = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata.len() != indexed_size,
+            Err(_) => true,
+        };
+        if damaged || key.size.is_some_and(|expected| expected != indexed_size) {
+            self.remove(&key.name).await;
+            return None;
+        }
+
+        let copied = {
+            let (path, dest, hashes) = (path.clone(), dest.to_path_buf(), key.hash.is_some());
+            tokio::task
+                ::spawn_blocking(move || copy_range(&path, 0, Some(indexed_size), &dest, offset, hashes)).await
+        };
+        let (size, actual_hash) = match copied {
+            Ok(Ok(copied)) => copied,
+            _ => {
+                self.forget(&key.name).await;
+                return None;
+            }
+        };
+
+        if size != indexed_size || key.hash != actual_hash {
+            self.remove(&key.name).await;
+            return None;
+        }
+
+        let now = SystemTime::now();
+        if let Some(entry) = self.state.lock().await.entries.get_mut(&key.name) {
+            entry.last_used = now;
+        }
+        tokio::task::spawn_blocking(move || {
+            let _ = fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(now));
+        });
+
+        Some(size)
+    }
+
+    /// Stores a copy of the `size` bytes at `offset` in `source` under `key`.
+    pub async fn insert(&self, key: &ChunkKey, source: &Path, offset: u64, size: u64) -> io::Result<()> {
+        {
+            let state = self.state.lock().await;
+            if size > state.quota_bytes || state.entries.contains_key(&key.name) {
+                return Ok(());
+            }
+        }
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+
+        // Several downloads can store the same chunk at once, each writes its own temporary file
+        let temp_path = self.dir.join(
+            format!("{}.{}.tmp", key.name, self.temp_counter.fetch_add(1, Ordering::Relaxed))
+        );
+        let copied = {
+            let (source, temp_path) = (source.to_path_buf(), temp_path.clone());
+            tokio::task
+                ::spawn_blocking(move || copy_range(&source, offset, Some(size), &temp_path, 0, false)).await
+                .map_err(io::Error::other)
+                .and_then(|copied| copied)
+        };
+        let result = match copied {
+            Ok((copied, _)) if copied != size => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            Ok(_) => tokio::fs::rename(&temp_path, self.entry_path(&key.name)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+
+        let quota_bytes = {
+            let mut state = self.state.lock().await;
+            let previous = state.entries.insert(key.name.clone(), CacheEntry {
+                size,
+                last_used: SystemTime::now(),
+            });
+            state.total_bytes = state.total_bytes - previous.map_or(0, |entry| entry.size) + size;
+            state.quota_bytes
+        };
+
+        self.prune(quota_bytes).await;
+        Ok(())
+    }
+
+    pub async fn info(&self) -> ChunkCacheInfo {
+        let state = self.state.lock().await;
+        ChunkCacheInfo {
+            path: self.dir.to_string_lossy().to_string(),
+            entries: state.entries.len(),
+            size_bytes: state.total_bytes,
+            quota_bytes: state.quota_bytes,
+        }
+    }
+
+    /// Sets the size quota and evicts chunks until the cache fits in it. A quota of zero
+    /// turns the cache off.
+    pub async fn set_quota(&self, quota_bytes: u64) -> u64 {
+        self.state.lock().await.quota_bytes = quota_bytes;
+        self.prune(quota_bytes).await
+    }
+
+    /// Evicts the least recently used chunks until at most `max_bytes` remain and returns
+    /// the number of bytes freed.
+    pub async fn prune(&self, max_bytes: u64) -> u64 {
+        let victims: Vec<(String, u64)> = {
+            let mut state = self.state.lock().await;
+            if state.total_bytes <= max_bytes {
+                return 0;
+            }
+
+            let mut by_age: Vec<(&String, &CacheEntry)> = state.entries.iter().collect();
+            by_age.sort_by_key(|(_, entry)| entry.last_used);
+
+            let mut remaining = state.total_bytes;
+            let victims: Vec<(String, u64)> = by_age
+                .into_iter()
+                .take_while(|(_, entry)| {
+                    let evict = remaining > max_bytes;
+                    remaining = remaining.saturating_sub(entry.size);
+                    evict
+                })
+                .map(|(name, entry)| (name.clone(), entry.size))
+                .collect();
+
+            for (name, size) in &victims {
+                state.entries.remove(name);
+                state.total_bytes = state.total_bytes.saturating_sub(*size);
+            }
+            victims
+        };
+
+        let mut freed = 0;
+        for (name, size) in victims {
+            if tokio::fs::remove_file(self.entry_path(&name)).await.is_ok() {
+                freed += size;
+            }
+        }
+        freed
+    }
+
+    pub async fn clear(&self) -> u64 {
+        self.prune(0).await
+    }
+
+    async fn forget(&self, name: &str) {
+        let mut state = self.state.lock().await;
+        if let Some(entry) = state.entries.remove(name) {
+            state.total_bytes = state.total_bytes.saturating_sub(entry.size);
+        }
+    }
+
+    async fn remove(&self, name: &str) {
+        self.forget(name).await;
+        let _ = tokio::fs::remove_file(self.entry_path(name)).await;
+    }
+}
+
+/// Copies `len` bytes, or everything up to the end, from `source` at `source_offset` into
+/// `dest` at `dest_offset`, creating `dest` and its folder when missing. Returns the number of
+/// bytes copied and, when `hashes` is set, their SHA-256.
+pub fn copy_range(
+    source: &Path,
+    source_offset: u64,
+    len: Option<u64>,
+    dest: &Path,
+    dest_offset: u64,
+    hashes: bool
+) -> io::Result<(u64, Option<String>)> {
+    let mut source = fs::File::open(source)?;
+    source.seek(SeekFrom::Start(source_offset))?;
+    let mut source = source.take(len.unwrap_or(u64::MAX));
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut dest = fs::File::options().write(true).create(true).truncate(false).open(dest)?;
+    dest.seek(SeekFrom::Start(dest_offset))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 256 * 1024];
+    let mut copied = 0u64;
+    loop {
+        let read = source.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        if hashes {
+            hasher.update(&buffer[..read]);
+        }
+        dest.write_all(&buffer[..read])?;
+        copied += read as u64;
+    }
+
+    Ok((copied, hashes.then(|| format!("{:x}", hasher.finalize()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty cache in the `cache` subfolder of a folder that is removed with the returned guard.
+    fn cache() -> (ChunkCache, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        (ChunkCache::with_dir(dir.path().join("cache")), dir)
+    }
+
+    const DATA: &[u8] = b"chunk data";
+    // SHA-256 of DATA
+    const DATA_HASH: &str = "83c24c9251ed5710267e07682a8f83542d6da7c0627372c12a9c412739248f9d";
+
+    #[tokio::test]
+    async fn returns_intact_entries() {
+        let (cache, dir) = cache();
+        let source = dir.path().join("source.chunk");
+        fs::write(&source, DATA).unwrap();
+
+        let key = ChunkKey::new("13.40", 1, Some(DATA_HASH), Some(DATA.len() as u64));
+        cache.insert(&key, &source, 0, DATA.len() as u64).await.unwrap();
+
+        let dest = dir.path().join("dest.chunk");
+        assert_eq!(cache.get(&key, &dest, 0).await, Some(DATA.len() as u64));
+        assert_eq!(fs::read(&dest).unwrap(), DATA);
+        assert_eq!(cache.info().await.entries, 1);
+    }
+
+    #[tokio::test]
+    async fn copies_chunks_between_file_offsets() {
+        let (cache, dir) = cache();
+        let source = dir.path().join("source.part");
+        fs::write(&source, [b"head-".as_slice(), DATA, b"-tail"].concat()).unwrap();
+
+        let key = ChunkKey::new("13.40", 1, Some(DATA_HASH), Some(DATA.len() as u64));
+        assert!(!cache.contains(&key).await);
+        cache.insert(&key, &source, 5, DATA.len() as u64).await.unwrap();
+        assert!(cache.contains(&key).await);
+
+        let dest = dir.path().join("dest.part");
+        fs::write(&dest, b"0123456789abcdefghij").unwrap();
+        assert_eq!(cache.get(&key, &dest, 4).await, Some(DATA.len() as u64));
+        assert_eq!(fs::read(&dest).unwrap(), [b"0123".as_slice(), DATA, b"efghij"].concat());
+    }
+
+    #[tokio::test]
+    async fn drops_truncated_entries() {
+        let (cache, dir) = cache();
+        let source = dir.path().join("source.chunk");
+        fs::write(&source, DATA).unwrap();
+
+        // Without a hash only the size can tell a damaged entry apart
+        let key = ChunkKey::new("13.40", 1, None, None);
+        cache.insert(&key, &source, 0, DATA.len() as u64).await.unwrap();
+        fs::write(cache.entry_path(&key.name), &DATA[..4]).unwrap();
+
+        let dest = dir.path().join("dest.chunk");
+        assert_eq!(cache.get(&key, &dest, 0).await, None);
+        assert_eq!(cache.info().await.entries, 0);
+    }
+
+    #[tokio::test]
+    async fn oversized_entries_leave_the_next_chunk_alone() {
+        let (cache, dir) = cache();
+        let source = dir.path().join("source.chunk");
+        fs::write(&source, DATA).unwrap();
+
+        let key = ChunkKey::new("13.40", 1, None, None);
+        cache.insert(&key, &source, 0, DATA.len() as u64).await.unwrap();
+        fs::write(cache.entry_path(&key.name), [DATA, b"overflow"].concat()).unwrap();
+
+        let dest = dir.path().join("dest.part");
+        let next_chunk = [vec![0u8; DATA.len()], b"next".to_vec()].concat();
+        fs::write(&dest, &next_chunk).unwrap();
+        assert_eq!(cache.get(&key, &dest, 0).await, None);
+        assert_eq!(fs::read(&dest).unwrap(), next_chunk);
+        assert_eq!(cache.info().await.entries, 0);
+    }
+
+    #[tokio::test]
+    async fn drops_entries_of_the_wrong_size() {
+        let (cache, dir) = cache();
+        let source = dir.path().join("source.chunk");
+        fs::write(&source, DATA).unwrap();
+
+        cache.insert(&ChunkKey::new("13.40", 1, None, None), &source, 0, DATA.len() as u64).await.unwrap();
+
+        let dest = dir.path().join("dest.chunk");
+        let key = ChunkKey::new("13.40", 1, None, Some(DATA.len() as u64 + 1));
+        assert_eq!(cache.get(&key, &dest, 0).await, None);
+        assert_eq!(cache.info().await.entries, 0);
+    }
+
+    #[tokio::test]
+    async fn drops_entries_with_the_wrong_hash() {
+        let (cache, dir) = cache();
+        let source = dir.path().join("source.chunk");
+        fs::write(&source, DATA).unwrap();
+
+        let key = ChunkKey::new("13.40", 1, Some(DATA_HASH), None);
+        cache.insert(&key, &source, 0, DATA.len() as u64).await.unwrap();
+        fs::write(cache.entry_path(&key.name), b"chunk dat!").unwrap();
+
+        let dest = dir.path().join("dest.chunk");
+        assert_eq!(cache.get(&key, &dest, 0).await, None);
+        assert!(!cache.entry_path(&key.name).exists());
+    }
+}
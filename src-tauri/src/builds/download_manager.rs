@@ -1,42 +1,382 @@
 use flate2::read::GzDecoder;
-use futures_util::StreamExt;
+use futures_util::future::{ BoxFuture, Shared };
+use futures_util::{ FutureExt, StreamExt };
 use indicatif::ProgressBar;
 use regex::Regex;
 use reqwest::Client;
 use serde::{ Deserialize, Serialize };
+use sha2::{ Digest, Sha256 };
 use std::fs::{ self, File };
+use std::future::Future;
 use std::io::{ self, Read, Seek, SeekFrom, Write };
 use std::path::{ Path, PathBuf };
 use std::sync::Arc;
+use std::sync::atomic::{ AtomicU64, Ordering };
 use std::time::{ Duration, Instant };
-use tauri::{ AppHandle, Emitter, State, Window, command };
-use tokio::fs::File as AsyncFile;
-use tokio::io::{ AsyncWriteExt, BufReader, BufWriter };
-use tokio::sync::Mutex;
+use tauri::{ AppHandle, Emitter, Manager, State, Window, command };
+use tokio::fs::{ File as AsyncFile, OpenOptions as AsyncOpenOptions };
+use tokio::io::{ AsyncSeekExt, AsyncWriteExt, BufWriter };
+use tokio::sync::{ Mutex, Notify };
+
+use super::chunk_cache::{ ChunkCache, ChunkCacheInfo, ChunkKey };
+use super::contained_path;
+use super::extract::{ self, ArchiveFormat, ExtractionStep };
+use super::hash::hash_file;
+use super::history::{ self, DownloadHistory, HistoryEntry, JobInfo, JobKind, JobOutcome, JobStats };
+use super::jobs::{ DownloadJob, JobRequest, JobState };
+use super::journal::DownloadJournal;
+use super::manifest::{ ChunkCompression, ChunkRef, ChunkedFile, MANIFEST_ACCEPT, ManifestFile };
+use super::metadata_cache::{ CachedResponse, MetadataCache };
+use super::mirrors::{ MirrorSet, MirrorStatus };
+use super::preflight::{ self, InstallRequirements, PreflightReport };
+use super::reuse::LocalChunks;
+use super::source::{ ChunkSource, version_dir };
+use super::throttle::BandwidthLimiter;
+use crate::endpoints::Endpoints;
+use crate::error::LauncherError;
 use tokio::time::timeout;
+use tokio_util::io::{ StreamReader, SyncIoBridge };
+use tokio_util::sync::CancellationToken;
+use zstd::stream::read::Decoder as ZstdDecoder;
 
 const MAX_RETRIES: usize = 3;
+const REQUIRED_FILE_RETRIES: usize = 30;
 const RETRY_DELAY_MS: u64 = 1000;
+const MAX_RETRY_DELAY_MS: u64 = 10_000;
 const UPDATE_INTERVAL_MS: u64 = 100;
 const REQUEST_TIMEOUT_SECS: u64 = 60;
-const BASE_URL: &str = "https://manifest.simplyblk.xyz";
+const DEFAULT_CHUNK_WORKERS: usize = 8;
+const MAX_CHUNK_WORKERS: usize = 20;
+const JOURNAL_SAVE_INTERVAL_MS: u64 = 1000;
+const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 2;
+const CHUNK_BUFFER_SIZE: usize = 256 * 1024;
+const STALL_TIMEOUT_SECS: u64 = 30;
+const REQUIRED_FILE_PRIORITY: i32 = 100;
+const CLEANUP_ATTEMPTS: u64 = 5;
+const CLEANUP_RETRY_DELAY_MS: u64 = 200;
+const EXTRACT_PIPE_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+const INCOMPLETE_MARKER: &str = ".solaris-incomplete";
+const SEGMENT_CONNECTIONS: u64 = 8;
+const MIN_SEGMENT_BYTES: u64 = 32 * 1024 * 1024;
 
 pub struct DownloadManager {
     active_downloads: Mutex<Vec<String>>,
     active_extractions: Mutex<Vec<String>>,
     download_speeds: Mutex<std::collections::HashMap<String, Vec<(Instant, u64)>>>,
+    unfinished_downloads: Mutex<std::collections::HashMap<String, DownloadJournal>>,
+    pause_requests: Mutex<Vec<String>>,
+    paused_downloads: Mutex<std::collections::HashMap<String, DownloadRequest>>,
+    range_validators: Mutex<std::collections::HashMap<String, RangeValidator>>,
+    job_stats: Mutex<std::collections::HashMap<String, Arc<JobStats>>>,
+    jobs: Mutex<std::collections::HashMap<String, DownloadJob>>,
+    cancellation_tokens: Mutex<std::collections::HashMap<String, CancellationToken>>,
+    history: DownloadHistory,
+    download_queue: Mutex<Vec<QueuedDownload>>,
+    max_concurrent_downloads: Mutex<usize>,
+    queue_changed: Notify,
+    bandwidth: Arc<BandwidthLimiter>,
+    manifest_mirrors: Arc<MirrorSet>,
+    version_mirrors: MirrorSet,
+    chunk_cache: Arc<ChunkCache>,
+    metadata_cache: MetadataCache,
 }
 
 impl DownloadManager {
-    pub fn new() -> Self {
+    pub fn with_endpoints(endpoints: &Endpoints) -> Self {
+        Self::with_mirrors(endpoints.manifest_mirrors.clone(), endpoints.version_mirrors.clone())
+    }
+
+    /// Builds a manager that fetches manifests and chunks from `manifest_mirrors` and the
+    /// version list from `version_mirrors`, in the given order until a latency probe reorders them.
+    pub fn with_mirrors<S: Into<String>>(
+        manifest_mirrors: impl IntoIterator<Item = S>,
+        version_mirrors: impl IntoIterator<Item = S>
+    ) -> Self {
         Self {
             active_downloads: Mutex::new(Vec::new()),
             active_extractions: Mutex::new(Vec::new()),
             download_speeds: Mutex::new(std::collections::HashMap::new()),
+            unfinished_downloads: Mutex::new(
+                DownloadJournal::load_all()
+                    .into_iter()
+                    .map(|journal| (journal.build_id.clone(), journal))
+                    .collect()
+            ),
+            pause_requests: Mutex::new(Vec::new()),
+            paused_downloads: Mutex::new(std::collections::HashMap::new()),
+            range_validators: Mutex::new(std::collections::HashMap::new()),
+            job_stats: Mutex::new(std::collections::HashMap::new()),
+            jobs: Mutex::new(std::collections::HashMap::new()),
+            cancellation_tokens: Mutex::new(std::collections::HashMap::new()),
+            history: DownloadHistory::new(),
+            download_queue: Mutex::new(Vec::new()),
+            max_concurrent_downloads: Mutex::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS),
+            queue_changed: Notify::new(),
+            bandwidth: Arc::new(BandwidthLimiter::new()),
+            manifest_mirrors: Arc::new(MirrorSet::new(manifest_mirrors)),
+            version_mirrors: MirrorSet::new(version_mirrors),
+            chunk_cache: Arc::new(ChunkCache::new()),
+            metadata_cache: MetadataCache::new(),
+        }
+    }
+
+    /// Removes `build_id` from the active downloads and frees its queue slot.
+    async fn release_slot(&self, build_id: &str) {
+        {
+            let mut active_downloads = self.active_downloads.lock().await;
+            if let Some(index) = active_downloads.iter().position(|id| id == build_id) {
+                active_downloads.remove(index);
+            }
+        }
+
+        self.clear_speed_data(build_id).await;
+        self.queue_changed.notify_waiters();
+    }
+
+    /// The token that stops the running job `build_id` when it is paused or cancelled.
+    async fn cancellation_token(&self, build_id: &str) -> CancellationToken {
+        self.cancellation_tokens
+            .lock().await
+            .entry(build_id.to_string())
+            .or_insert_with(CancellationToken::new)
+            .clone()
+    }
+
+    /// Stores the validator of a transfer that was stopped, so `resume_download` can continue
+    /// it if the stop turns out to be a pause.
+    async fn keep_range_validator(&self, build_id: &str, validator: Option<RangeValidator>) {
+        if let Some(validator) = validator {
+            self.range_validators.lock().await.insert(build_id.to_string(), validator);
+        }
+    }
+
+    /// Stops the running download `build_id` so it can be resumed later. Updates and repairs
+    /// are not journaled, so they cannot be paused and have to be cancelled instead.
+    async fn pause(&self, build_id: &str) -> Result<bool, LauncherError> {
+        if !self.active_downloads.lock().await.iter().any(|id| id == build_id) {
+            return Ok(false);
+        }
+
+        let resumable = self.jobs
+            .lock().await
+            .get(build_id)
+            .is_none_or(|job| matches!(job.request, JobRequest::Download(_)));
+        if !resumable {
+            return Err(LauncherError::InvalidRequest {
+                message: format!("{} is an update or repair, which cannot be paused", build_id),
+            });
+        }
+
+        let token = self.cancellation_tokens.lock().await.get(build_id).cloned();
+        let Some(token) = token else {
+            return Ok(false);
+        };
+
+        // The pause request has to be visible before the job sees its token cancelled,
+        // otherwise it would treat the stop as a cancellation and delete its partial data
+        self.pause_requests.lock().await.push(build_id.to_string());
+        token.cancel();
+        Ok(true)
+    }
+
+    async fn take_pause_request(&self, build_id: &str) -> bool {
+        let mut pause_requests = self.pause_requests.lock().await;
+        let position = pause_requests.iter().position(|id| id == build_id);
+        if let Some(index) = position {
+            pause_requests.remove(index);
+        }
+        position.is_some()
+    }
+
+    pub async fn set_mirrors(&self, manifest_mirrors: &[String], version_mirrors: &[String]) {
+        self.manifest_mirrors.set_base_urls(manifest_mirrors).await;
+        self.version_mirrors.set_base_urls(version_mirrors).await;
+    }
+
+    pub async fn probe_mirrors(&self) {
+        let Ok(client) = Client::builder().connect_timeout(Duration::from_secs(5)).build() else {
+            return;
+        };
+
+        tokio::join!(
+            self.manifest_mirrors.probe_latency(&client, ""),
+            self.version_mirrors.probe_latency(&client, "versions.json")
+        );
+    }
+
+    async fn enqueue(
+        &self,
+        window: &Window,
+        build_id: &str,
+        priority: i32,
+        required: bool
+    ) -> Result<(), LauncherError> {
+        let mut queue = self.download_queue.lock().await;
+        if queue.iter().any(|queued| queued.build_id == build_id) {
+            return Err(LauncherError::AlreadyActive { message: "Download already queued".into() });
+        }
+
+        insert_by_priority(&mut queue, QueuedDownload {
+            build_id: build_id.to_string(),
+            priority,
+            required,
+        });
+
+        emit_queue(window, &queue);
+        Ok(())
+    }
+
+    /// Waits until `build_id` is close enough to the front of the queue to take a free slot,
+    /// then moves it from the queue into `active_downloads`. Required files do not wait.
+    async fn wait_for_slot(&self, window: &Window, build_id: &str) -> Result<(), LauncherError> {
+        let cancel = self.cancellation_token(build_id).await;
+        loop {
+            let notified = self.queue_changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            {
+                let mut queue = self.download_queue.lock().await;
+                let Some(position) = queue.iter().position(|queued| queued.build_id == build_id) else {
+                    return Err(LauncherError::Cancelled);
+                };
+
+                let max_concurrent = *self.max_concurrent_downloads.lock().await;
+                let mut active_downloads = self.active_downloads.lock().await;
+                if takes_slot(&queue, position, max_concurrent.saturating_sub(active_downloads.len())) {
+                    queue.remove(position);
+                    active_downloads.push(build_id.to_string());
+                    emit_queue(window, &queue);
+                    self.job_stats.lock().await.insert(build_id.to_string(), Arc::new(JobStats::new()));
+                    return Ok(());
+                }
+            }
+
+            tokio::select! {
+                _ = notified => {}
+                _ = cancel.cancelled() => {
+                    remove_from_queue(self, build_id).await;
+                    return Err(LauncherError::Cancelled);
+                }
+            }
+        }
+    }
+
+    /// Runs `job` as the tracked job `build_id`. Refuses to start while another job with that
+    /// ID is still running, replaces one that is paused and makes sure the job ends in a final
+    /// state whatever `job` returns.
+    async fn run_job<T>(
+        &self,
+        window: &Window,
+        build_id: &str,
+        request: JobRequest,
+        job: impl Future<Output = Result<T, LauncherError>>
+    ) -> Result<T, LauncherError> {
+        {
+            let mut jobs = self.jobs.lock().await;
+            let previous = jobs.get(build_id).map(|job| job.state);
+            if previous.is_some_and(|state| !state.is_finished()) {
+                return Err(LauncherError::AlreadyActive { message: "Download already in progress".into() });
+            }
+
+            // A paused job that is started again is replaced, so nothing may resume it later
+            if previous == Some(JobState::Paused) {
+                self.paused_downloads.lock().await.remove(build_id);
+                self.pause_requests.lock().await.retain(|id| id != build_id);
+            }
+
+            let job = DownloadJob::new(build_id, request);
+            let _ = window.emit("download:state", job.clone());
+            jobs.insert(build_id.to_string(), job);
+            self.cancellation_tokens.lock().await.insert(build_id.to_string(), CancellationToken::new());
+        }
+
+        let result = job.await;
+        self.cancellation_tokens.lock().await.remove(build_id);
+
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(build_id).filter(|job| !job.state.is_finished()) {
+            match &result {
+                Ok(_) => job.set_state(JobState::Completed),
+                Err(e) => {
+                    job.set_state(JobState::of_error(e));
+                    job.error = Some(e.clone());
+                }
+            }
+            let _ = window.emit("download:state", job.clone());
+        }
+        result
+    }
+
+    async fn set_job_state(&self, window: &Window, build_id: &str, state: JobState) {
+        if let Some(job) = self.jobs.lock().await.get_mut(build_id) {
+            job.set_state(state);
+            let _ = window.emit("download:state", job.clone());
         }
     }
 
+    /// Emits `progress` and keeps it as the latest progress of its job.
+    async fn report_progress(&self, window: &Window, progress: DownloadProgress) {
+        if let Some(job) = self.jobs.lock().await.get_mut(&progress.build_id) {
+            job.progress = Some(progress.clone());
+        }
+        let _ = window.emit("download:progress", progress);
+    }
+
+    /// Statistics of the running job `build_id`, kept until `record_history` files them.
+    async fn job_stats(&self, build_id: &str) -> Arc<JobStats> {
+        self.job_stats
+            .lock().await
+            .entry(build_id.to_string())
+            .or_insert_with(|| Arc::new(JobStats::new()))
+            .clone()
+    }
+
+    /// Adds the finished job `job` to the download history together with its statistics.
+    async fn record_history(&self, job: JobInfo, outcome: JobOutcome, error: Option<&LauncherError>) {
+        let stats = self.job_stats
+            .lock().await
+            .remove(&job.build_id)
+            .unwrap_or_else(|| Arc::new(JobStats::new()));
+        // Losing a history entry is not worth failing the job over
+        let _ = self.history.record(stats.finish(job, outcome, error)).await;
+    }
+
+    /// Files the outcome of the update or repair `job` in the history and reports a failure
+    /// with `download:failed`, whatever stage it failed at.
+    async fn finish_job<T>(
+        &self,
+        window: &Window,
+        job: JobInfo,
+        result: Result<T, LauncherError>
+    ) -> Result<T, LauncherError> {
+        match &result {
+            Ok(_) => self.record_history(job, JobOutcome::Completed, None).await,
+            Err(e) => {
+                let _ = window.emit("download:failed", DownloadFailed {
+                    build_id: job.build_id.clone(),
+                    error: e.clone(),
+                });
+                self.record_history(job, JobOutcome::of_error(e), Some(e)).await;
+            }
+        }
+        result
+    }
+
+    /// The mirror manifest jobs currently start on, recorded as their source.
+    async fn manifest_source(&self) -> String {
+        self.manifest_mirrors.ordered().await.into_iter().next().unwrap_or_default()
+    }
+
     async fn update_speed_data(&self, build_id: &str, bytes: u64) -> f64 {
+        let speed = self.measure_speed(build_id, bytes).await;
+        if let Some(stats) = self.job_stats.lock().await.get(build_id) {
+            stats.observe_speed(speed);
+        }
+        speed
+    }
+
+    async fn measure_speed(&self, build_id: &str, bytes: u64) -> f64 {
         let mut speeds = self.download_speeds.lock().await;
         let speed_data = speeds.entry(build_id.to_string()).or_insert_with(Vec::new);
 
@@ -89,7 +429,22 @@ pub struct ExtractionProgress {
     eta: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize)]
+pub struct QueuedDownload {
+    build_id: String,
+    priority: i32,
+    #[serde(skip)]
+    required: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DownloadQueued {
+    build_id: String,
+    position: usize,
+    priority: i32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DownloadRequest {
     build_id: String,
     url: String,
@@ -98,6 +453,94 @@ pub struct DownloadRequest {
     delete_after_extract: bool,
     use_manifest: Option<bool>,
     version: Option<String>,
+    max_workers: Option<usize>,
+    priority: Option<i32>,
+    bandwidth_limit: Option<u64>,
+    /// Extract a zip or tar.gz archive while it downloads instead of storing it first.
+    stream_extract: Option<bool>,
+    /// Where a manifest install reads its manifest and chunks from: an http(s) base URL, a
+    /// file:// URL or a directory path. The configured mirrors when unset.
+    source: Option<String>,
+    /// Set for files the game needs to launch, see [`download_game_file`].
+    #[serde(skip)]
+    required: bool,
+}
+
+impl DownloadRequest {
+    fn streams_extraction(&self) -> bool {
+        self.extract && self.stream_extract.unwrap_or(false) && !self.use_manifest.unwrap_or(false)
+    }
+
+    fn chunk_source(&self) -> Result<ChunkSource, LauncherError> {
+        ChunkSource::parse(self.source.as_deref()).map_err(|message| LauncherError::InvalidRequest {
+            message,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct DownloadFailed {
+    build_id: String,
+    error: LauncherError,
+}
+
+#[derive(Serialize)]
+pub struct UpdateResult {
+    success: bool,
+    message: String,
+    changed_files: usize,
+    removed_files: usize,
+    unchanged_files: usize,
+    /// Bytes received from the network.
+    downloaded_bytes: u64,
+    full_install_bytes: u64,
+    /// Bytes of the new version that were not downloaded: the unchanged files and the chunks
+    /// copied from the old files or the chunk cache.
+    bytes_saved: u64,
+}
+
+#[derive(Serialize)]
+pub struct VerifyReport {
+    version: String,
+    checked_files: usize,
+    missing_files: Vec<String>,
+    truncated_files: Vec<String>,
+    corrupted_files: Vec<String>,
+    healthy: bool,
+}
+
+#[derive(Serialize)]
+pub struct RepairResult {
+    success: bool,
+    message: String,
+    repaired_files: Vec<String>,
+    /// Bytes received from the network.
+    downloaded_bytes: u64,
+}
+
+/// Data served from the network or, when no mirror could be reached, from the last copy
+/// fetched. `fetched_at` is when a server last confirmed the data, in seconds since the epoch.
+#[derive(Serialize)]
+pub struct Cached<T> {
+    data: T,
+    stale: bool,
+    fetched_at: u64,
+}
+
+#[derive(Serialize)]
+pub struct BandwidthLimits {
+    global: Option<u64>,
+    download: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Queued,
+    Active,
+    Paused,
+    Unfinished,
+    Inactive,
 }
 
 #[derive(Serialize)]
@@ -108,24 +551,23 @@ pub struct DownloadResult {
     extracted_path: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ChunkedFile {
-    #[serde(rename = "ChunksIds")]
-    chunks_ids: Vec<i32>,
-    #[serde(rename = "File")]
-    file: String,
-    #[serde(rename = "FileSize")]
-    file_size: i64,
+/// Higher priorities go first, equal priorities keep their arrival order.
+fn insert_by_priority(queue: &mut Vec<QueuedDownload>, queued: QueuedDownload) {
+    let position = queue
+        .iter()
+        .position(|other| other.priority < queued.priority)
+        .unwrap_or(queue.len());
+    queue.insert(position, queued);
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ManifestFile {
-    #[serde(rename = "Name")]
-    pub name: String,
-    #[serde(rename = "Chunks")]
-    pub chunks: Vec<ChunkedFile>,
-    #[serde(rename = "Size")]
-    pub size: i64,
+fn emit_queue(window: &Window, queue: &[QueuedDownload]) {
+    for (position, queued) in queue.iter().enumerate() {
+        let _ = window.emit("download:queued", DownloadQueued {
+            build_id: queued.build_id.clone(),
+            position,
+            priority: queued.priority,
+        });
+    }
 }
 
 fn format_time(seconds: f64) -> String {
@@ -150,582 +592,3591 @@ fn format_time(seconds: f64) -> String {
 pub async fn is_download_active(
     build_id: String,
     download_manager: State<'_, DownloadManager>
-) -> Result<bool, String> {
-    let active_downloads = download_manager.active_downloads.lock().await;
-    Ok(active_downloads.contains(&build_id))
+) -> Result<bool, LauncherError> {
+    // Queued jobs count too, otherwise the same build could be started a second time while
+    // it waits for a slot
+    let queued = download_manager.download_queue
+        .lock().await
+        .iter()
+        .any(|queued| queued.build_id == build_id);
+    let running = download_manager.jobs
+        .lock().await
+        .get(&build_id)
+        .is_some_and(|job| !job.state.is_finished());
+
+    Ok(queued || running || download_manager.active_downloads.lock().await.contains(&build_id))
 }
 
 #[command]
 pub async fn is_extraction_active(
     build_id: String,
     download_manager: State<'_, DownloadManager>
-) -> Result<bool, String> {
+) -> Result<bool, LauncherError> {
     let active_extractions = download_manager.active_extractions.lock().await;
     Ok(active_extractions.contains(&build_id))
 }
 
 #[command]
 pub async fn cancel_download(
+    window: Window,
     build_id: String,
     download_manager: State<'_, DownloadManager>
-) -> Result<bool, String> {
-    let mut active_downloads = download_manager.active_downloads.lock().await;
-    if let Some(index) = active_downloads.iter().position(|id| id == &build_id) {
-        active_downloads.remove(index);
-        download_manager.clear_speed_data(&build_id).await;
-        Ok(true)
-    } else {
-        Ok(false)
+) -> Result<bool, LauncherError> {
+    let token = download_manager.cancellation_tokens.lock().await.get(&build_id).cloned();
+    if let Some(token) = token {
+        // The job aborts whatever it is waiting on and removes its own temporary files
+        token.cancel();
+        return Ok(true);
     }
-}
 
-#[command]
-pub async fn cancel_extraction(
-    build_id: String,
-    download_manager: State<'_, DownloadManager>
-) -> Result<bool, String> {
-    let mut active_extractions = download_manager.active_extractions.lock().await;
-    if let Some(index) = active_extractions.iter().position(|id| id == &build_id) {
-        active_extractions.remove(index);
-        Ok(true)
-    } else {
-        Ok(false)
+    // Each map is unlocked before anything else is locked or awaited, run_job locks `jobs`
+    // and then `paused_downloads`
+    let paused = download_manager.paused_downloads.lock().await.remove(&build_id);
+    if let Some(request) = paused {
+        let temp_dest = format!("{}.download", request.destination);
+        remove_temp_path(Path::new(&temp_dest)).await;
+        if request.use_manifest.unwrap_or(false) {
+            DownloadJournal::remove(&build_id);
+            download_manager.unfinished_downloads.lock().await.remove(&build_id);
+        }
+        download_manager.set_job_state(&window, &build_id, JobState::Cancelled).await;
+        return Ok(true);
     }
-}
 
-#[command]
-pub async fn get_available_versions() -> Result<Vec<String>, String> {
-    let client = Client::new();
-    let versions_url = format!("{}/versions.json", "https://cdn.solarisfn.dev");
+    let unfinished = download_manager.unfinished_downloads.lock().await.remove(&build_id);
+    if let Some(journal) = unfinished {
+        remove_temp_path(Path::new(&journal.install_path)).await;
+        DownloadJournal::remove(&build_id);
+        return Ok(true);
+    }
 
-    let response = client
-        .get(&versions_url)
-        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
-        .send().await
-        .map_err(|e| format!("Network error fetching versions: {}", e))?;
+    Ok(false)
+}
 
-    if response.status().is_success() {
-        response.json::<Vec<String>>().await.map_err(|e| format!("Failed to parse versions: {}", e))
-    } else {
-        Err(format!("Failed to fetch versions: HTTP {}", response.status()))
+/// Removes the temporary file or directory a stopped job left at `path`. Chunk tasks that
+/// were aborted mid-write can hold their .part file open for a moment longer, which keeps
+/// Windows from deleting it, so the removal is retried for a short while.
+async fn remove_temp_path(path: &Path) {
+    for attempt in 1..=CLEANUP_ATTEMPTS {
+        let removed = match tokio::fs::symlink_metadata(path).await {
+            Ok(metadata) if metadata.is_dir() => tokio::fs::remove_dir_all(path).await,
+            Ok(_) => tokio::fs::remove_file(path).await,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return;
+            }
+            Err(e) => Err(e),
+        };
+        if removed.is_ok() {
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(CLEANUP_RETRY_DELAY_MS * attempt)).await;
     }
 }
 
-#[command]
-pub async fn get_manifest_for_version(version: String) -> Result<ManifestFile, String> {
-    let re = Regex::new(r"Release-(\d+\.\d+)").map_err(|e| format!("Regex error: {}", e))?;
-
-    if let Some(caps) = re.captures(&version) {
-        let extracted_version = caps.get(1).unwrap().as_str();
+/// Whether the queued download at `position` may start while `free_slots` slots are left.
+/// Required files start right away, the game cannot launch without them and they are small
+/// next to a build. Everything else takes the free slots in queue order.
+fn takes_slot(queue: &[QueuedDownload], position: usize, free_slots: usize) -> bool {
+    queue[position].required ||
+        queue[..position]
+            .iter()
+            .filter(|queued| !queued.required)
+            .count() < free_slots
+}
 
-        let client = Client::new();
-        let manifest_url = format!(
-            "{}/{}/{}.manifest",
-            BASE_URL,
-            extracted_version,
-            extracted_version
-        );
+async fn remove_from_queue(download_manager: &DownloadManager, build_id: &str) -> bool {
+    let mut queue = download_manager.download_queue.lock().await;
+    let Some(position) = queue.iter().position(|queued| queued.build_id == build_id) else {
+        return false;
+    };
 
-        let mut retries = 0;
-        let mut last_error = String::new();
+    queue.remove(position);
+    download_manager.queue_changed.notify_waiters();
+    true
+}
 
-        while retries < MAX_RETRIES {
-            match
-                client
-                    .get(&manifest_url)
-                    .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
-                    .send().await
-            {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        return response
-                            .json::<ManifestFile>().await
-                            .map_err(|e| format!("Failed to parse manifest: {}", e));
-                    } else if response.status().is_server_error() {
-                        last_error = format!("Server error: HTTP {}", response.status());
-                        retries += 1;
-                        tokio::time::sleep(
-                            Duration::from_millis(RETRY_DELAY_MS * (retries as u64))
-                        ).await;
-                        continue;
-                    } else {
-                        return Err(format!("Failed to fetch manifest: HTTP {}", response.status()));
-                    }
-                }
-                Err(e) => {
-                    last_error = format!("Network error: {}", e);
-                    retries += 1;
-                    tokio::time::sleep(
-                        Duration::from_millis(RETRY_DELAY_MS * (retries as u64))
-                    ).await;
-                }
-            }
-        }
+/// Every job of this session with its state, request and latest progress, oldest first. Lets
+/// a reloaded webview rebuild its progress UI without waiting for the next event.
+#[command]
+pub async fn get_downloads(
+    download_manager: State<'_, DownloadManager>
+) -> Result<Vec<DownloadJob>, LauncherError> {
+    let mut jobs: Vec<DownloadJob> = download_manager.jobs.lock().await.values().cloned().collect();
+    jobs.sort_by_key(|job| job.created_at);
+    Ok(jobs)
+}
 
-        Err(
-            format!(
-                "Failed to fetch manifest after {} retries. Last error: {}",
-                MAX_RETRIES,
-                last_error
-            )
-        )
-    } else {
-        Err("Version format is incorrect".to_string())
-    }
+#[command]
+pub async fn get_download_queue(
+    download_manager: State<'_, DownloadManager>
+) -> Result<Vec<QueuedDownload>, LauncherError> {
+    Ok(download_manager.download_queue.lock().await.clone())
 }
 
+/// Moves a queued download to `position`. The queue stays ordered by priority, so the
+/// download takes on a priority between those of its new neighbours.
 #[command]
-pub async fn download_build(
+pub async fn move_queued_download(
     window: Window,
-    request: DownloadRequest,
+    build_id: String,
+    position: usize,
     download_manager: State<'_, DownloadManager>
-) -> Result<DownloadResult, String> {
-    let build_id = request.build_id.clone();
+) -> Result<bool, LauncherError> {
+    let mut queue = download_manager.download_queue.lock().await;
+    let Some(current) = queue.iter().position(|queued| queued.build_id == build_id) else {
+        return Ok(false);
+    };
 
-    {
-        let active_downloads = download_manager.active_downloads.lock().await;
-        if active_downloads.contains(&build_id) {
-            return Err("Download already in progress".into());
-        }
+    let mut queued = queue.remove(current);
+    let position = position.min(queue.len());
+    if let Some(next) = queue.get(position) {
+        queued.priority = queued.priority.max(next.priority);
     }
-
-    {
-        let mut active_downloads = download_manager.active_downloads.lock().await;
-        active_downloads.push(build_id.clone());
+    if let Some(previous) = position.checked_sub(1).and_then(|index| queue.get(index)) {
+        queued.priority = queued.priority.min(previous.priority);
     }
+    queue.insert(position, queued);
 
-    let dest_path = Path::new(&request.destination);
-    if let Some(parent) = dest_path.parent() {
-        if !parent.exists() {
-            std::fs
-                ::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create destination directory: {}", e))?;
-        }
-    }
+    emit_queue(&window, &queue);
+    download_manager.queue_changed.notify_waiters();
+    Ok(true)
+}
 
-    let temp_dest = format!("{}.download", request.destination);
+#[command]
+pub async fn set_download_priority(
+    window: Window,
+    build_id: String,
+    priority: i32,
+    download_manager: State<'_, DownloadManager>
+) -> Result<bool, LauncherError> {
+    let mut queue = download_manager.download_queue.lock().await;
+    let Some(current) = queue.iter().position(|queued| queued.build_id == build_id) else {
+        return Ok(false);
+    };
 
-    let use_manifest = request.use_manifest.unwrap_or(false);
+    // Re-inserted like a new arrival, behind the downloads that already had this priority
+    let mut queued = queue.remove(current);
+    queued.priority = priority;
+    insert_by_priority(&mut queue, queued);
 
-    let download_result = if use_manifest {
-        if let Some(version) = request.version.clone() {
-            download_manifest(
-                window.clone(),
-                build_id.clone(),
-                &version,
-                &temp_dest,
-                &download_manager
-            ).await
-        } else {
-            Err("Version is required for manifest-based download".into())
-        }
-    } else {
-        download_file(
-            window.clone(),
-            build_id.clone(),
-            &request.url,
-            &temp_dest,
-            &download_manager
-        ).await
+    emit_queue(&window, &queue);
+    download_manager.queue_changed.notify_waiters();
+    Ok(true)
+}
+
+#[command]
+pub async fn set_max_concurrent_downloads(
+    limit: usize,
+    download_manager: State<'_, DownloadManager>
+) -> Result<(), LauncherError> {
+    *download_manager.max_concurrent_downloads.lock().await = limit.max(1);
+    download_manager.queue_changed.notify_waiters();
+    Ok(())
+}
+
+#[command]
+pub async fn get_bandwidth_limits(
+    build_id: Option<String>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<BandwidthLimits, LauncherError> {
+    let download = match build_id {
+        Some(build_id) => download_manager.bandwidth.download_limit(&build_id).await,
+        None => None,
     };
 
-    {
-        let mut active_downloads = download_manager.active_downloads.lock().await;
-        if let Some(index) = active_downloads.iter().position(|id| id == &build_id) {
-            active_downloads.remove(index);
-        }
-        download_manager.clear_speed_data(&build_id).await;
-    }
+    Ok(BandwidthLimits {
+        global: download_manager.bandwidth.global_limit().await,
+        download,
+    })
+}
 
-    match download_result {
-        Ok(_) => {
-            let file_metadata = fs
-                ::metadata(&temp_dest)
-                .map_err(|e| format!("Failed to verify downloaded file: {}", e))?;
+#[command]
+pub async fn set_bandwidth_limit(
+    bytes_per_second: Option<u64>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<(), LauncherError> {
+    download_manager.bandwidth.set_global_limit(bytes_per_second).await;
+    Ok(())
+}
 
-            if file_metadata.len() == 0 && !use_manifest {
-                let _ = fs::remove_file(&temp_dest);
-                return Err("Downloaded file is empty. The URL may be invalid.".into());
-            }
+#[command]
+pub async fn set_download_bandwidth_limit(
+    build_id: String,
+    bytes_per_second: Option<u64>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<(), LauncherError> {
+    download_manager.bandwidth.set_download_limit(&build_id, bytes_per_second).await;
+    Ok(())
+}
 
-            if Path::new(&request.destination).exists() {
-                let _ = fs::remove_file(&request.destination);
-            }
+#[command]
+pub async fn pause_download(
+    build_id: String,
+    download_manager: State<'_, DownloadManager>
+) -> Result<bool, LauncherError> {
+    download_manager.pause(&build_id).await
+}
 
-            fs
-                ::rename(&temp_dest, &request.destination)
-                .map_err(|e| format!("Failed to finalize download: {}", e))?;
+#[command]
+pub async fn get_download_status(
+    build_id: String,
+    download_manager: State<'_, DownloadManager>
+) -> Result<DownloadStatus, LauncherError> {
+    let queued = download_manager.download_queue
+        .lock().await
+        .iter()
+        .any(|queued| queued.build_id == build_id);
 
-            Ok(DownloadResult {
-                success: true,
-                message: "Download completed successfully".into(),
-                path: Some(request.destination),
-                extracted_path: None,
-            })
-        }
-        Err(e) => {
-            let _ = std::fs::remove_file(&temp_dest);
-            let _ = window.emit("download:failed", build_id);
-            Err(e.to_string())
-        }
+    if queued {
+        Ok(DownloadStatus::Queued)
+    } else if download_manager.active_downloads.lock().await.contains(&build_id) {
+        Ok(DownloadStatus::Active)
+    } else if download_manager.paused_downloads.lock().await.contains_key(&build_id) {
+        Ok(DownloadStatus::Paused)
+    } else if download_manager.unfinished_downloads.lock().await.contains_key(&build_id) {
+        Ok(DownloadStatus::Unfinished)
+    } else {
+        Ok(DownloadStatus::Inactive)
     }
 }
 
-async fn download_manifest(
+#[command]
+pub async fn get_unfinished_downloads(
+    download_manager: State<'_, DownloadManager>
+) -> Result<Vec<DownloadJournal>, LauncherError> {
+    let unfinished_downloads = download_manager.unfinished_downloads.lock().await;
+    Ok(unfinished_downloads.values().cloned().collect())
+}
+
+#[command]
+pub async fn resume_download(
     window: Window,
     build_id: String,
-    version: &str,
-    install_path: &str,
-    download_manager: &State<'_, DownloadManager>
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let manifest = match get_manifest_for_version(version.to_string()).await {
-        Ok(manifest) => manifest,
-        Err(e) => {
-            return Err(format!("Failed to get manifest: {}", e).into());
+    download_manager: State<'_, DownloadManager>
+) -> Result<DownloadResult, LauncherError> {
+    let paused_request = download_manager.paused_downloads.lock().await.remove(&build_id);
+
+    let request = match paused_request {
+        Some(request) => request,
+        None => {
+            let journal = download_manager.unfinished_downloads
+                .lock().await
+                .get(&build_id)
+                .cloned()
+                .or_else(|| DownloadJournal::load(&build_id))
+                .ok_or_else(|| LauncherError::NotFound {
+                    message: format!("No paused or unfinished download found for {}", build_id),
+                })?;
+
+            DownloadRequest {
+                build_id,
+                url: journal.url,
+                destination: journal.destination,
+                extract: false,
+                delete_after_extract: false,
+                use_manifest: Some(true),
+                version: Some(journal.version),
+                max_workers: journal.max_workers,
+                priority: None,
+                bandwidth_limit: None,
+                stream_extract: None,
+                source: journal.source,
+                required: false,
+            }
         }
     };
 
-    let total_size = manifest.size;
-    let mut completed_size: i64 = 0;
-    let start_time = std::time::Instant::now();
+    start_download(window, request, true, download_manager).await
+}
+
+#[command]
+pub async fn cancel_extraction(
+    build_id: String,
+    download_manager: State<'_, DownloadManager>
+) -> Result<bool, LauncherError> {
+    let mut active_extractions = download_manager.active_extractions.lock().await;
+    if let Some(index) = active_extractions.iter().position(|id| id == &build_id) {
+        active_extractions.remove(index);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+#[command]
+pub async fn get_available_versions(
+    download_manager: State<'_, DownloadManager>
+) -> Result<Cached<Vec<String>>, LauncherError> {
+    fetch_versions(&Client::new(), &download_manager).await
+}
+
+#[command]
+pub async fn get_cdn_mirrors(
+    download_manager: State<'_, DownloadManager>
+) -> Result<Vec<MirrorStatus>, LauncherError> {
+    let mut mirrors = download_manager.manifest_mirrors.status().await;
+    mirrors.extend(download_manager.version_mirrors.status().await);
+    Ok(mirrors)
+}
+
+#[command]
+pub async fn get_chunk_cache_info(
+    download_manager: State<'_, DownloadManager>
+) -> Result<ChunkCacheInfo, LauncherError> {
+    Ok(download_manager.chunk_cache.info().await)
+}
+
+#[command]
+pub async fn set_chunk_cache_quota(
+    quota_bytes: u64,
+    download_manager: State<'_, DownloadManager>
+) -> Result<ChunkCacheInfo, LauncherError> {
+    download_manager.chunk_cache.set_quota(quota_bytes).await;
+    Ok(download_manager.chunk_cache.info().await)
+}
+
+/// Evicts least recently used chunks until the cache holds at most `max_bytes`, or fits its
+/// quota when no size is given. Returns the number of bytes freed.
+#[command]
+pub async fn prune_chunk_cache(
+    max_bytes: Option<u64>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<u64, LauncherError> {
+    let max_bytes = match max_bytes {
+        Some(max_bytes) => max_bytes,
+        None => download_manager.chunk_cache.info().await.quota_bytes,
+    };
+    Ok(download_manager.chunk_cache.prune(max_bytes).await)
+}
+
+#[command]
+pub async fn clear_chunk_cache(download_manager: State<'_, DownloadManager>) -> Result<u64, LauncherError> {
+    Ok(download_manager.chunk_cache.clear().await)
+}
+
+/// Finished jobs, newest first, optionally only those of `build_id`.
+#[command]
+pub async fn get_download_history(
+    build_id: Option<String>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<Vec<HistoryEntry>, LauncherError> {
+    let mut entries = download_manager.history.entries().await;
+    entries.retain(|entry| build_id.as_ref().is_none_or(|build_id| &entry.build_id == build_id));
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Writes the history entries listed in `entry_ids`, or all of them, to `path` as JSON and
+/// returns how many were written.
+#[command]
+pub async fn export_download_history(
+    path: String,
+    entry_ids: Option<Vec<String>>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<usize, LauncherError> {
+    let mut entries = download_manager.history.entries().await;
+    if let Some(entry_ids) = &entry_ids {
+        entries.retain(|entry| entry_ids.contains(&entry.id));
+    }
+
+    history::export(&entries, Path::new(&path)).await?;
+    Ok(entries.len())
+}
+
+#[command]
+pub async fn get_manifest_for_version(
+    version: String,
+    download_manager: State<'_, DownloadManager>
+) -> Result<Cached<ManifestFile>, LauncherError> {
+    fetch_manifest_cached(&Client::new(), &download_manager, &version).await
+}
+
+async fn fetch_versions(
+    client: &Client,
+    download_manager: &DownloadManager
+) -> Result<Cached<Vec<String>>, LauncherError> {
+    fetch_cached(
+        client,
+        &download_manager.version_mirrors,
+        &download_manager.metadata_cache,
+        MirroredDocument {
+            what: "versions",
+            path: "versions.json",
+            cache_key: "versions",
+            accept: None,
+            attempts: 1,
+        }
+    ).await
+}
+
+async fn fetch_manifest_cached(
+    client: &Client,
+    download_manager: &DownloadManager,
+    version: &str
+) -> Result<Cached<ManifestFile>, LauncherError> {
+    let extracted_version = manifest_version(version).ok_or_else(|| LauncherError::InvalidVersion {
+        version: version.to_string(),
+    })?;
+
+    let manifest: Cached<ManifestFile> = fetch_cached(
+        client,
+        &download_manager.manifest_mirrors,
+        &download_manager.metadata_cache,
+        MirroredDocument {
+            what: "manifest",
+            path: &format!("{0}/{0}.manifest", extracted_version),
+            cache_key: &format!("manifest-{}", extracted_version),
+            accept: Some(MANIFEST_ACCEPT),
+            attempts: MAX_RETRIES,
+        }
+    ).await?;
+
+    manifest.data.validate().map_err(|message| LauncherError::Parse {
+        message: format!("Invalid manifest for {}: {}", version, message),
+    })?;
+    Ok(manifest)
+}
+
+async fn fetch_manifest(
+    client: &Client,
+    download_manager: &DownloadManager,
+    version: &str
+) -> Result<ManifestFile, LauncherError> {
+    fetch_manifest_cached(client, download_manager, version).await.map(|cached| cached.data)
+}
+
+/// Fetches the manifest of `version` from `source`. A server other than the mirrors is cached
+/// under its own key; a directory is read as it is, without touching the network.
+async fn fetch_source_manifest(
+    client: &Client,
+    download_manager: &DownloadManager,
+    version: &str,
+    source: &ChunkSource
+) -> Result<ManifestFile, LauncherError> {
+    let extracted_version = manifest_version(version).ok_or_else(|| LauncherError::InvalidVersion {
+        version: version.to_string(),
+    })?;
+
+    let manifest: ManifestFile = match source {
+        ChunkSource::Mirrors => {
+            return fetch_manifest(client, download_manager, version).await;
+        }
+        ChunkSource::Http(base_url) => {
+            fetch_cached(
+                client,
+                &MirrorSet::new([base_url.as_str()]),
+                &download_manager.metadata_cache,
+                MirroredDocument {
+                    what: "manifest",
+                    path: &format!("{0}/{0}.manifest", extracted_version),
+                    cache_key: &format!("manifest-{}-{}", extracted_version, base_url),
+                    accept: Some(MANIFEST_ACCEPT),
+                    attempts: MAX_RETRIES,
+                }
+            ).await?.data
+        }
+        ChunkSource::Directory(root) => {
+            let manifest_path = version_dir(root, &extracted_version).await
+                .join(format!("{}.manifest", extracted_version));
+            let data = tokio::fs::read(&manifest_path).await
+                .map_err(|e| LauncherError::io(&manifest_path, e))?;
+            serde_json::from_slice(&data).map_err(|e| LauncherError::Parse {
+                message: format!("Failed to parse {}: {}", manifest_path.display(), e),
+            })?
+        }
+    };
+
+    manifest.validate().map_err(|message| LauncherError::Parse {
+        message: format!("Invalid manifest for {}: {}", version, message),
+    })?;
+    Ok(manifest)
+}
+
+/// A JSON document every mirror of a set serves at the same path, see [`fetch_cached`].
+struct MirroredDocument<'a> {
+    /// Names the document in errors.
+    what: &'a str,
+    path: &'a str,
+    /// Key of the copy kept in the metadata cache.
+    cache_key: &'a str,
+    /// Sent as the Accept header when given.
+    accept: Option<&'a str>,
+    /// How often a server error is retried on each mirror.
+    attempts: usize,
+}
+
+/// Fetches `document` from the healthiest mirror that answers, revalidating the cached copy
+/// with its ETag and Last-Modified. When no mirror answers the cached copy is returned and
+/// marked stale. When a mirror answers with something we cannot parse, such as a manifest
+/// version this launcher does not read, the cached copy is known to be out of date and the
+/// parse error is returned instead.
+async fn fetch_cached<T: serde::de::DeserializeOwned>(
+    client: &Client,
+    mirrors: &MirrorSet,
+    cache: &MetadataCache,
+    document: MirroredDocument<'_>
+) -> Result<Cached<T>, LauncherError> {
+    let MirroredDocument { what, path, cache_key: key, accept, attempts } = document;
+    // A cached body that no longer parses is as good as no cache at all
+    let mut cached = cache
+        .load(key).await
+        .and_then(|entry| serde_json::from_str::<T>(&entry.body).ok().map(|data| (entry, data)));
+
+    let mut last_error = LauncherError::other(format!("No {} mirrors configured", what));
+    let mut unreadable = None;
+
+    for base_url in mirrors.ordered().await {
+        let url = format!("{}/{}", base_url, path);
+        let mut attempt = 0;
+
+        loop {
+            let mut request = client.get(&url).timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS));
+            if let Some(accept) = accept {
+                request = request.header(reqwest::header::ACCEPT, accept);
+            }
+            if let Some((entry, _)) = &cached {
+                if let Some(etag) = &entry.etag {
+                    request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            attempt += 1;
+
+            let retry = match request.send().await {
+                Ok(response) if response.status() == reqwest::StatusCode::NOT_MODIFIED => {
+                    match cached.take() {
+                        Some((mut entry, data)) => {
+                            mirrors.record_success(&base_url).await;
+                            entry.fetched_at = unix_time();
+                            let _ = cache.store(key, &entry).await;
+                            return Ok(Cached { data, stale: false, fetched_at: entry.fetched_at });
+                        }
+                        None => {
+                            last_error = LauncherError::Http { status: 304, url: url.clone() };
+                            false
+                        }
+                    }
+                }
+                Ok(response) if response.status().is_success() => {
+                    let header = |name: reqwest::header::HeaderName| {
+                        response.headers()
+                            .get(name)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string)
+                    };
+                    let etag = header(reqwest::header::ETAG);
+                    let last_modified = header(reqwest::header::LAST_MODIFIED);
+
+                    match response.text().await {
+                        Ok(body) =>
+                            match serde_json::from_str::<T>(&body) {
+                                Ok(data) => {
+                                    mirrors.record_success(&base_url).await;
+                                    let entry = CachedResponse {
+                                        etag,
+                                        last_modified,
+                                        fetched_at: unix_time(),
+                                        body,
+                                    };
+                                    // Failing to cache only matters the next time we are offline
+                                    let _ = cache.store(key, &entry).await;
+                                    return Ok(Cached { data, stale: false, fetched_at: entry.fetched_at });
+                                }
+                                Err(e) => {
+                                    last_error = LauncherError::Parse {
+                                        message: format!("Failed to parse {}: {}", what, e),
+                                    };
+                                    unreadable = Some(last_error.clone());
+                                    false
+                                }
+                            }
+                        Err(e) => {
+                            last_error = LauncherError::Network {
+                                message: format!("Network error fetching {}: {}", what, e),
+                                url: Some(url.clone()),
+                            };
+                            true
+                        }
+                    }
+                }
+                Ok(response) => {
+                    last_error = LauncherError::Http {
+                        status: response.status().as_u16(),
+                        url: url.clone(),
+                    };
+                    response.status().is_server_error()
+                }
+                Err(e) => {
+                    last_error = LauncherError::Network {
+                        message: format!("Network error fetching {}: {}", what, e),
+                        url: Some(url.clone()),
+                    };
+                    true
+                }
+            };
+
+            if !retry || attempt >= attempts {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(RETRY_DELAY_MS * (attempt as u64))).await;
+        }
+
+        // Move on to the next mirror, this one either refused the request or kept failing
+        mirrors.record_failure(&base_url).await;
+    }
+
+    if let Some(error) = unreadable {
+        return Err(error);
+    }
+    match cached {
+        Some((entry, data)) => Ok(Cached { data, stale: true, fetched_at: entry.fetched_at }),
+        None => Err(last_error),
+    }
+}
+
+fn unix_time() -> u64 {
+    std::time::SystemTime
+        ::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[command]
+pub async fn preflight_install(
+    request: DownloadRequest,
+    download_manager: State<'_, DownloadManager>
+) -> Result<PreflightReport, LauncherError> {
+    preflight_request(&request, false, &download_manager).await
+}
+
+/// Works out how much `request` is going to write and checks it against the target volume.
+/// Bytes a resumable download already has on disk are not counted again.
+async fn preflight_request(
+    request: &DownloadRequest,
+    resume: bool,
+    download_manager: &DownloadManager
+) -> Result<PreflightReport, LauncherError> {
+    let client = chunk_client()?;
+    let temp_dest = format!("{}.download", request.destination);
+
+    let requirements = if request.use_manifest.unwrap_or(false) {
+        let version = request.version.as_deref().ok_or_else(|| LauncherError::InvalidRequest {
+            message: "Version is required for manifest-based download".into(),
+        })?;
+        let manifest = fetch_source_manifest(
+            &client,
+            download_manager,
+            version,
+            &request.chunk_source()?
+        ).await?;
+        let total_bytes = manifest.size.max(0) as u64;
+        let written_bytes = DownloadJournal::load(&request.build_id)
+            .filter(|journal| journal.version == version && journal.install_path == temp_dest)
+            .map_or(0, |journal| journal.completed_bytes);
+        // Every downloaded chunk may also be stored in the cache, up to its quota
+        let cache = download_manager.chunk_cache.info().await;
+
+        InstallRequirements {
+            total_bytes: Some(total_bytes),
+            written_bytes,
+            temp_bytes: 0,
+            cache_bytes: cache.quota_bytes
+                .saturating_sub(cache.size_bytes)
+                .min(total_bytes.saturating_sub(written_bytes)),
+            cache_path: Some(PathBuf::from(cache.path)),
+            largest_file_bytes: manifest.chunks
+                .iter()
+                .map(|chunked_file| chunked_file.file_size.max(0) as u64)
+                .max()
+                .unwrap_or(0),
+            longest_relative_path: manifest.chunks
+                .iter()
+                .map(|chunked_file| chunked_file.file.len() + ".part".len())
+                .max()
+                .unwrap_or(0),
+        }
+    } else {
+        let total_bytes = match client.head(&request.url).send().await {
+            Ok(response) if response.status().is_success() => response.content_length(),
+            _ => None,
+        };
+        let written_bytes = if resume {
+            tokio::fs::metadata(&temp_dest).await.map_or(0, |metadata| metadata.len())
+        } else {
+            0
+        };
+
+        // Extracted files take at least as much room as the archive they come from, which
+        // only needs room of its own when it is stored before extraction
+        let stores_archive = request.extract && !request.streams_extraction();
+        InstallRequirements {
+            total_bytes,
+            written_bytes,
+            temp_bytes: if stores_archive { total_bytes.unwrap_or(0) } else { 0 },
+            cache_bytes: 0,
+            cache_path: None,
+            largest_file_bytes: total_bytes.unwrap_or(0),
+            longest_relative_path: 0,
+        }
+    };
+
+    tokio::task
+        ::spawn_blocking(move || preflight::check(Path::new(&temp_dest), &requirements)).await
+        .map_err(|e| LauncherError::other(format!("Preflight check failed: {}", e)))
+}
+
+#[command]
+pub async fn download_build(
+    window: Window,
+    request: DownloadRequest,
+    download_manager: State<'_, DownloadManager>
+) -> Result<DownloadResult, LauncherError> {
+    start_download(window, request, false, download_manager).await
+}
+
+/// Downloads a single file the game needs to launch, such as a fractured pak, to `dest`.
+/// It runs as a regular job keyed by `dest`, so it reports `download:progress` and can be
+/// cancelled, but it starts right away instead of waiting for a build download to finish.
+#[command]
+pub async fn download_game_file(
+    window: Window,
+    url: String,
+    dest: String,
+    download_manager: State<'_, DownloadManager>
+) -> Result<(), LauncherError> {
+    let request = DownloadRequest {
+        build_id: dest.clone(),
+        url,
+        destination: dest,
+        extract: false,
+        delete_after_extract: false,
+        use_manifest: Some(false),
+        version: None,
+        max_workers: None,
+        priority: Some(REQUIRED_FILE_PRIORITY),
+        bandwidth_limit: None,
+        stream_extract: None,
+        source: None,
+        required: true,
+    };
+
+    start_download(window, request, false, download_manager).await?;
+    Ok(())
+}
+
+async fn start_download(
+    window: Window,
+    request: DownloadRequest,
+    resume: bool,
+    download_manager: State<'_, DownloadManager>
+) -> Result<DownloadResult, LauncherError> {
+    let build_id = request.build_id.clone();
+    let job = JobRequest::Download(request.clone());
+
+    download_manager.run_job(
+        &window,
+        &build_id,
+        job,
+        run_download(window.clone(), request, resume, &download_manager)
+    ).await
+}
+
+async fn run_download(
+    window: Window,
+    request: DownloadRequest,
+    resume: bool,
+    download_manager: &State<'_, DownloadManager>
+) -> Result<DownloadResult, LauncherError> {
+    let build_id = request.build_id.clone();
+    let cancel = download_manager.cancellation_token(&build_id).await;
+    let temp_dest = format!("{}.download", request.destination);
+    let use_manifest = request.use_manifest.unwrap_or(false);
+
+    // A job stopped before or while it is queued still ends like a running one
+    let prepared = match prepare_download(&window, &request, resume, download_manager).await {
+        Ok(chunk_source) =>
+            match download_manager.wait_for_slot(&window, &build_id).await {
+                // Jobs that ran while this one was queued may have taken the space it counted on
+                Ok(()) => check_preflight(&request, resume, download_manager).await.map(|()| chunk_source),
+                Err(e) => Err(e),
+            }
+        Err(e) => Err(e),
+    };
+    let started = prepared.is_ok();
+
+    if started && request.bandwidth_limit.is_some() {
+        download_manager.bandwidth.set_download_limit(&build_id, request.bandwidth_limit).await;
+    }
+
+    let download_result = match &prepared {
+        Err(e) => Err(e.clone()),
+        Ok(chunk_source) if use_manifest => {
+            if let Some(version) = request.version.clone() {
+                let mut journal = match DownloadJournal::load(&build_id) {
+                    Some(journal) if journal.version == version && journal.install_path == temp_dest => {
+                        journal
+                    }
+                    stale => {
+                        if let Some(stale) = stale {
+                            let _ = fs::remove_dir_all(&stale.install_path);
+                        }
+                        DownloadJournal::new(
+                            &build_id,
+                            &version,
+                            &request.url,
+                            &request.destination,
+                            &temp_dest,
+                            request.max_workers,
+                            request.source.clone()
+                        )
+                    }
+                };
+                journal.max_workers = request.max_workers;
+                download_manager.unfinished_downloads.lock().await.remove(&build_id);
+
+                download_manifest(&window, chunk_source, &mut journal, download_manager).await.map(|()| None)
+            } else {
+                Err(LauncherError::InvalidRequest {
+                    message: "Version is required for manifest-based download".into(),
+                })
+            }
+        }
+        Ok(_) if request.streams_extraction() => {
+            download_manager.set_job_state(&window, &build_id, JobState::Downloading).await;
+            stream_extract_archive(
+                &window,
+                &build_id,
+                &request.url,
+                Path::new(&request.destination),
+                download_manager
+            ).await.map(Some)
+        }
+        Ok(_) => {
+            download_manager.set_job_state(&window, &build_id, JobState::Downloading).await;
+            download_file(
+                window.clone(),
+                build_id.clone(),
+                &request.url,
+                &temp_dest,
+                resume,
+                request.required,
+                download_manager
+            ).await.map(|()| None)
+        }
+    };
+
+    download_manager.release_slot(&build_id).await;
+    let cancelled = cancel.is_cancelled() && download_result.is_err();
+    let paused = download_manager.take_pause_request(&build_id).await && cancelled;
+
+    let job = JobInfo {
+        build_id: build_id.clone(),
+        kind: JobKind::Download,
+        version: request.version.clone(),
+        source: match &request.source {
+            _ if !use_manifest => request.url.clone(),
+            Some(source) if !matches!(request.chunk_source(), Ok(ChunkSource::Mirrors)) => source.clone(),
+            _ => download_manager.manifest_source().await,
+        },
+    };
+
+    if paused {
+        download_manager.record_history(job, JobOutcome::Paused, None).await;
+        download_manager.paused_downloads.lock().await.insert(build_id.clone(), request);
+        download_manager.set_job_state(&window, &build_id, JobState::Paused).await;
+        let _ = window.emit("download:paused", build_id);
+
+        return Ok(DownloadResult {
+            success: false,
+            message: "Download paused".into(),
+            path: None,
+            extracted_path: None,
+        });
+    }
+
+    download_manager.bandwidth.set_download_limit(&build_id, None).await;
+
+    let result = match download_result {
+        Ok(Some(extracted_path)) =>
+            Ok(DownloadResult {
+                success: true,
+                message: "Download and extraction completed successfully".into(),
+                path: None,
+                extracted_path: Some(extracted_path.to_string_lossy().to_string()),
+            }),
+        Ok(None) => finish_download(&window, &request, &temp_dest, download_manager).await,
+        Err(_) if cancelled => Err(LauncherError::Cancelled),
+        Err(e) => Err(e),
+    };
+
+    match &result {
+        Ok(_) => download_manager.record_history(job, JobOutcome::Completed, None).await,
+        Err(e) => download_manager.record_history(job, JobOutcome::of_error(e), Some(e)).await,
+    }
+
+    let Err(error) = result else {
+        return result;
+    };
+    // A job that never started has written nothing, and a resumed one keeps what it had
+    if started {
+        if !use_manifest {
+            remove_temp_path(Path::new(&temp_dest)).await;
+        } else if cancelled {
+            remove_temp_path(Path::new(&temp_dest)).await;
+            DownloadJournal::remove(&build_id);
+        } else if let Some(journal) = DownloadJournal::load(&build_id) {
+            // Keep the partial install so resume_download can pick up where this left off
+            download_manager.unfinished_downloads.lock().await.insert(build_id.clone(), journal);
+        }
+    }
+    let _ = window.emit("download:failed", DownloadFailed {
+        build_id,
+        error: error.clone(),
+    });
+    Err(error)
+}
+
+/// Checks that `request` can run and queues it. Returns where its chunks come from.
+async fn prepare_download(
+    window: &Window,
+    request: &DownloadRequest,
+    resume: bool,
+    download_manager: &DownloadManager
+) -> Result<ChunkSource, LauncherError> {
+    check_preflight(request, resume, download_manager).await?;
+    let chunk_source = request.chunk_source()?;
+
+    let dest_path = Path::new(&request.destination);
+    if let Some(parent) = dest_path.parent() {
+        if !parent.exists() {
+            std::fs
+                ::create_dir_all(parent)
+                .map_err(|e| LauncherError::io(parent, e))?;
+        }
+    }
+
+    download_manager.enqueue(window, &request.build_id, request.priority.unwrap_or(0), request.required).await?;
+    Ok(chunk_source)
+}
+
+async fn check_preflight(
+    request: &DownloadRequest,
+    resume: bool,
+    download_manager: &DownloadManager
+) -> Result<(), LauncherError> {
+    let report = preflight_request(request, resume, download_manager).await?;
+    if !report.passed {
+        return Err(LauncherError::Preflight { message: report.failure_message() });
+    }
+    Ok(())
+}
+
+/// Moves a finished download from `temp_dest` to its destination and extracts it when the
+/// request asks for that.
+async fn finish_download(
+    window: &Window,
+    request: &DownloadRequest,
+    temp_dest: &str,
+    download_manager: &State<'_, DownloadManager>
+) -> Result<DownloadResult, LauncherError> {
+    let build_id = &request.build_id;
+    let use_manifest = request.use_manifest.unwrap_or(false);
+
+    download_manager.set_job_state(window, build_id, JobState::Finalizing).await;
+    let file_metadata = fs::metadata(temp_dest).map_err(|e| LauncherError::io(temp_dest, e))?;
+
+    if file_metadata.len() == 0 && !use_manifest {
+        let _ = fs::remove_file(temp_dest);
+        return Err(LauncherError::Integrity {
+            message: "Downloaded file is empty. The URL may be invalid.".into(),
+            file: None,
+            chunk_id: None,
+        });
+    }
+
+    if Path::new(&request.destination).exists() {
+        let _ = fs::remove_file(&request.destination);
+    }
+
+    fs::rename(temp_dest, &request.destination).map_err(|e| LauncherError::io(&request.destination, e))?;
+
+    if use_manifest {
+        DownloadJournal::remove(build_id);
+    }
+
+    if !request.extract || use_manifest {
+        return Ok(DownloadResult {
+            success: true,
+            message: "Download completed successfully".into(),
+            path: Some(request.destination.clone()),
+            extracted_path: None,
+        });
+    }
+
+    let extracted_path = extract_build(
+        window.clone(),
+        build_id.clone(),
+        PathBuf::from(&request.destination),
+        download_manager
+    ).await?;
+
+    if request.delete_after_extract {
+        fs::remove_file(&request.destination).map_err(|e| LauncherError::io(&request.destination, e))?;
+    }
+
+    Ok(DownloadResult {
+        success: true,
+        message: "Download and extraction completed successfully".into(),
+        path: if request.delete_after_extract {
+            None
+        } else {
+            Some(request.destination.clone())
+        },
+        extracted_path: Some(extracted_path.to_string_lossy().to_string()),
+    })
+}
+
+async fn extract_build(
+    window: Window,
+    build_id: String,
+    archive_path: PathBuf,
+    download_manager: &State<'_, DownloadManager>
+) -> Result<PathBuf, LauncherError> {
+    let format = ArchiveFormat::detect(&archive_path).ok_or_else(|| LauncherError::Extraction {
+        message: "Unsupported archive format".into(),
+        path: Some(archive_path.to_string_lossy().to_string()),
+    })?;
+    let target = extract::extraction_target(&archive_path, format);
+    let temp_target = PathBuf::from(format!("{}.extracting", target.to_string_lossy()));
+
+    {
+        let mut active_extractions = download_manager.active_extractions.lock().await;
+        if active_extractions.contains(&build_id) {
+            return Err(LauncherError::AlreadyActive { message: "Extraction already in progress".into() });
+        }
+        active_extractions.push(build_id.clone());
+    }
+    download_manager.set_job_state(&window, &build_id, JobState::Extracting).await;
+
+    if temp_target.exists() {
+        let _ = fs::remove_dir_all(&temp_target);
+    }
+
+    let archive_name = archive_path.to_string_lossy().to_string();
+    let result = {
+        let window = window.clone();
+        let build_id = build_id.clone();
+        let temp_target = temp_target.clone();
+
+        tauri::async_runtime
+            ::spawn_blocking(move || {
+                let download_manager = window.app_handle().state::<DownloadManager>();
+                let start_time = Instant::now();
+                let mut last_update = Instant::now();
+
+                extract::extract_archive(&archive_path, &temp_target, format, |step| {
+                    if !download_manager.active_extractions.blocking_lock().contains(&build_id) {
+                        return false;
+                    }
+
+                    if last_update.elapsed().as_millis() > (UPDATE_INTERVAL_MS as u128) {
+                        report_extraction_step(&window, &download_manager, &build_id, &step, start_time);
+                        last_update = Instant::now();
+                    }
+
+                    true
+                })
+            }).await
+            .unwrap_or_else(|e|
+                Err(LauncherError::Extraction {
+                    message: format!("Extraction task failed: {}", e),
+                    path: Some(archive_name),
+                })
+            )
+    };
+
+    {
+        let mut active_extractions = download_manager.active_extractions.lock().await;
+        if let Some(index) = active_extractions.iter().position(|id| id == &build_id) {
+            active_extractions.remove(index);
+        }
+    }
+
+    let result = result.and_then(|_| {
+        if target.exists() {
+            fs::remove_dir_all(&target).map_err(|e| LauncherError::io(&target, e))?;
+        }
+
+        fs::rename(&temp_target, &target).map_err(|e| LauncherError::io(&target, e))
+    });
+
+    match result {
+        Ok(()) => {
+            let _ = window.emit("extraction:completed", build_id);
+            Ok(target)
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&temp_target);
+            let _ = window.emit("extraction:failed", build_id);
+            Err(e)
+        }
+    }
+}
+
+/// Emits `extraction:progress` for `step` of an extraction that began at `start_time` and
+/// keeps it as the latest extraction progress of the job. Runs on the extraction thread.
+fn report_extraction_step(
+    window: &Window,
+    download_manager: &DownloadManager,
+    build_id: &str,
+    step: &ExtractionStep,
+    start_time: Instant
+) {
+    let elapsed = start_time.elapsed().as_secs_f64();
+    let eta_seconds = if step.fraction > 0.0 {
+        (elapsed / step.fraction) * (1.0 - step.fraction)
+    } else {
+        f64::INFINITY
+    };
+
+    let progress = ExtractionProgress {
+        build_id: build_id.to_string(),
+        percentage: step.fraction * 100.0,
+        current_file: step.current_file.to_string(),
+        total_files: step.total_files,
+        processed_files: step.processed_files,
+        eta: format_time(eta_seconds),
+    };
+    if let Some(job) = download_manager.jobs.blocking_lock().get_mut(build_id) {
+        job.extraction = Some(progress.clone());
+    }
+    let _ = window.emit("extraction:progress", progress);
+}
+
+/// Downloads the archive at `url` and extracts it as the bytes arrive, so the archive itself
+/// never touches the disk. Files go to `<target>.extracting`, which holds an
+/// `INCOMPLETE_MARKER` file until everything is in place and it is renamed to the target.
+/// A failed run leaves that directory behind with the marker naming the error. A stream
+/// cannot be continued, so a pause or cancel discards it and the next run starts over.
+async fn stream_extract_archive(
+    window: &Window,
+    build_id: &str,
+    url: &str,
+    archive_path: &Path,
+    download_manager: &DownloadManager
+) -> Result<PathBuf, LauncherError> {
+    let format = ArchiveFormat::detect(archive_path)
+        .filter(ArchiveFormat::can_stream)
+        .ok_or_else(|| LauncherError::InvalidRequest {
+            message: "Only zip and tar.gz archives can be extracted while they download".into(),
+        })?;
+    let target = extract::extraction_target(archive_path, format);
+    let temp_target = PathBuf::from(format!("{}.extracting", target.to_string_lossy()));
+    let marker_path = temp_target.join(INCOMPLETE_MARKER);
+
+    {
+        let mut active_extractions = download_manager.active_extractions.lock().await;
+        if active_extractions.iter().any(|id| id == build_id) {
+            return Err(LauncherError::AlreadyActive { message: "Extraction already in progress".into() });
+        }
+        active_extractions.push(build_id.to_string());
+    }
+
+    let result = async {
+        let extraction = pipe_archive(window, build_id, url, &temp_target, format, download_manager);
+        extract_marked(&temp_target, url, extraction).await?;
+
+        if target.exists() {
+            tokio::fs::remove_dir_all(&target).await.map_err(|e| LauncherError::io(&target, e))?;
+        }
+        tokio::fs::rename(&temp_target, &target).await.map_err(|e| LauncherError::io(&target, e))
+    }.await;
+
+    {
+        let mut active_extractions = download_manager.active_extractions.lock().await;
+        if let Some(index) = active_extractions.iter().position(|id| id == build_id) {
+            active_extractions.remove(index);
+        }
+    }
+
+    match result {
+        Ok(()) => {
+            let _ = window.emit("extraction:completed", build_id);
+            Ok(target)
+        }
+        Err(LauncherError::Cancelled) => {
+            remove_temp_path(&temp_target).await;
+            let _ = window.emit("extraction:failed", build_id);
+            Err(LauncherError::Cancelled)
+        }
+        Err(e) => {
+            let _ = tokio::fs::write(
+                &marker_path,
+                format!("Extraction of {} did not finish: {}\n", url, e)
+            ).await;
+            let _ = window.emit("extraction:failed", build_id);
+            Err(e)
+        }
+    }
+}
+
+/// Runs `extraction` into a fresh `temp_target` that holds an `INCOMPLETE_MARKER` file
+/// until it succeeds. The marker is written before anything else, so even a crash halfway
+/// leaves the tree marked.
+async fn extract_marked(
+    temp_target: &Path,
+    url: &str,
+    extraction: impl Future<Output = Result<(), LauncherError>>
+) -> Result<(), LauncherError> {
+    let marker_path = temp_target.join(INCOMPLETE_MARKER);
+
+    remove_temp_path(temp_target).await;
+    tokio::fs::create_dir_all(temp_target).await.map_err(|e| LauncherError::io(temp_target, e))?;
+    tokio::fs
+        ::write(&marker_path, format!("Extraction of {} did not finish\n", url)).await
+        .map_err(|e| LauncherError::io(&marker_path, e))?;
+
+    extraction.await?;
+
+    tokio::fs::remove_file(&marker_path).await.map_err(|e| LauncherError::io(&marker_path, e))
+}
+
+/// Feeds the body of `url` through a bounded pipe into an extractor on a blocking thread,
+/// reporting download progress as bytes arrive and extraction progress as entries land.
+async fn pipe_archive(
+    window: &Window,
+    build_id: &str,
+    url: &str,
+    temp_target: &Path,
+    format: ArchiveFormat,
+    download_manager: &DownloadManager
+) -> Result<(), LauncherError> {
+    let client = Client::builder().connect_timeout(std::time::Duration::from_secs(10)).build()?;
+    let cancel = download_manager.cancellation_token(build_id).await;
+    let stats = download_manager.job_stats(build_id).await;
+
+    let sent = tokio::select! {
+        biased;
+        _ = cancel.cancelled() => {
+            return Err(LauncherError::Cancelled);
+        }
+        sent = client.get(url).send() => sent,
+    };
+    let response = sent.map_err(|e| LauncherError::Network {
+        message: format!("Failed to download: {}", e),
+        url: Some(url.to_string()),
+    })?;
+    if !response.status().is_success() {
+        return Err(LauncherError::Http { status: response.status().as_u16(), url: url.to_string() });
+    }
+    let total_bytes = response.content_length();
+
+    let (mut writer, reader) = tokio::io::duplex(EXTRACT_PIPE_BUFFER_SIZE);
+    let reader = SyncIoBridge::new(reader);
+    let extraction = {
+        let window = window.clone();
+        let build_id = build_id.to_string();
+        let temp_target = temp_target.to_path_buf();
+        let cancel = cancel.clone();
+
+        tauri::async_runtime::spawn_blocking(move || {
+            let download_manager = window.app_handle().state::<DownloadManager>();
+            let start_time = Instant::now();
+            let mut last_update = Instant::now();
+
+            extract::extract_stream(reader, total_bytes, &temp_target, format, |step| {
+                if !download_manager.active_extractions.blocking_lock().contains(&build_id) {
+                    // cancel_extraction, the download is of no use without its extraction
+                    cancel.cancel();
+                }
+                if cancel.is_cancelled() {
+                    return false;
+                }
+
+                if last_update.elapsed().as_millis() > (UPDATE_INTERVAL_MS as u128) {
+                    report_extraction_step(&window, &download_manager, &build_id, &step, start_time);
+                    last_update = Instant::now();
+                }
+
+                true
+            })
+        })
+    };
+
+    let mut stream = response.bytes_stream();
+    let mut received_bytes = 0u64;
+    let mut last_update = Instant::now();
+    let transfer = loop {
+        let next = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                break Err(LauncherError::Cancelled);
+            }
+            next = timeout(Duration::from_secs(STALL_TIMEOUT_SECS), stream.next()) => next,
+        };
+
+        let piece = match next {
+            Ok(Some(Ok(piece))) => piece,
+            Ok(Some(Err(e))) => {
+                break Err(LauncherError::Network {
+                    message: format!("Error downloading file: {}", e),
+                    url: Some(url.to_string()),
+                });
+            }
+            Ok(None) => {
+                break Ok(());
+            }
+            Err(_) => {
+                break Err(LauncherError::Network {
+                    message: format!(
+                        "Download stalled - no data received for {} seconds",
+                        STALL_TIMEOUT_SECS
+                    ),
+                    url: Some(url.to_string()),
+                });
+            }
+        };
+
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                break Err(LauncherError::Cancelled);
+            }
+            _ = download_manager.bandwidth.acquire(build_id, piece.len() as u64) => {}
+        }
+        stats.add_bytes(piece.len() as u64);
+        if writer.write_all(&piece).await.is_err() {
+            // The extractor stopped reading, its result tells why
+            break Ok(());
+        }
+        received_bytes += piece.len() as u64;
+
+        if last_update.elapsed().as_millis() > (UPDATE_INTERVAL_MS as u128) {
+            let speed = download_manager.update_speed_data(build_id, received_bytes).await;
+            let (percentage, eta) = match total_bytes {
+                Some(total) if total > 0 => {
+                    let eta_seconds = if speed > 0.0 {
+                        (total.saturating_sub(received_bytes) as f64) / speed
+                    } else {
+                        f64::INFINITY
+                    };
+                    (((received_bytes as f64) / (total as f64)) * 100.0, format_time(eta_seconds))
+                }
+                _ => (0.0, "Unknown".to_string()),
+            };
+
+            download_manager.report_progress(window, DownloadProgress {
+                build_id: build_id.to_string(),
+                percentage,
+                downloaded_bytes: received_bytes,
+                total_bytes: total_bytes.unwrap_or(0),
+                speed,
+                eta,
+            }).await;
+            last_update = Instant::now();
+        }
+    };
+
+    // Closing the pipe is how the extractor learns the archive ended
+    drop(writer);
+    let extracted = extraction.await.unwrap_or_else(|e|
+        Err(LauncherError::Extraction {
+            message: format!("Extraction task failed: {}", e),
+            path: Some(temp_target.to_string_lossy().to_string()),
+        })
+    );
+
+    if cancel.is_cancelled() {
+        return Err(LauncherError::Cancelled);
+    }
+    // A broken transfer also fails the extraction, but the network error is the one to report
+    transfer?;
+    extracted?;
+
+    let _ = window.emit("download:completed", build_id);
+    Ok(())
+}
+
+#[command]
+pub async fn update_build(
+    window: Window,
+    build_id: String,
+    from_version: String,
+    to_version: String,
+    path: String,
+    download_manager: State<'_, DownloadManager>
+) -> Result<UpdateResult, LauncherError> {
+    let job = JobRequest::Update {
+        from_version: from_version.clone(),
+        to_version: to_version.clone(),
+        path: path.clone(),
+    };
+
+    download_manager.run_job(
+        &window,
+        &build_id,
+        job,
+        run_update(&window, &build_id, from_version, to_version, &path, &download_manager)
+    ).await
+}
+
+async fn run_update(
+    window: &Window,
+    build_id: &str,
+    from_version: String,
+    to_version: String,
+    path: &str,
+    download_manager: &DownloadManager
+) -> Result<UpdateResult, LauncherError> {
+    let job = JobInfo {
+        build_id: build_id.to_string(),
+        kind: JobKind::Update,
+        version: Some(to_version.clone()),
+        source: download_manager.manifest_source().await,
+    };
+    let result = apply_update(window, build_id, &from_version, &to_version, path, download_manager).await;
+    download_manager.finish_job(window, job, result).await
+}
+
+async fn apply_update(
+    window: &Window,
+    build_id: &str,
+    from_version: &str,
+    to_version: &str,
+    path: &str,
+    download_manager: &DownloadManager
+) -> Result<UpdateResult, LauncherError> {
+    download_manager.set_job_state(window, build_id, JobState::ResolvingManifest).await;
+    let client = chunk_client()?;
+    let from_manifest = fetch_manifest(&client, download_manager, from_version).await?;
+    let to_manifest = fetch_manifest(&client, download_manager, to_version).await?;
+    let extracted_version = manifest_version(to_version).ok_or_else(|| LauncherError::InvalidVersion {
+        version: to_version.to_string(),
+    })?;
+
+    let base_path = Path::new(path);
+    let old_files: std::collections::HashMap<&str, &ChunkedFile> = from_manifest.chunks
+        .iter()
+        .map(|chunked_file| (chunked_file.file.as_str(), chunked_file))
+        .collect();
+    let new_files: std::collections::HashSet<&str> = to_manifest.chunks
+        .iter()
+        .map(|chunked_file| chunked_file.file.as_str())
+        .collect();
+
+    // A file is only rebuilt when its chunk list changed between the two versions or it is
+    // missing locally, everything else is left untouched on disk
+    let changed_files: Vec<&ChunkedFile> = to_manifest.chunks
+        .iter()
+        .filter(|chunked_file| {
+            let unchanged = old_files
+                .get(chunked_file.file.as_str())
+                .is_some_and(|old| {
+                    old.chunks_ids == chunked_file.chunks_ids && old.file_size == chunked_file.file_size
+                });
+            !unchanged || !base_path.join(&chunked_file.file).exists()
+        })
+        .collect();
+    let removed_files: Vec<&str> = from_manifest.chunks
+        .iter()
+        .map(|chunked_file| chunked_file.file.as_str())
+        .filter(|file| !new_files.contains(file))
+        .collect();
+
+    // Changed files mostly keep their chunks, which are copied out of the old files
+    let rewritten = changed_files
+        .iter()
+        .map(|chunked_file| chunked_file.file.clone())
+        .collect();
+    let local_chunks = LocalChunks::index(base_path, &from_manifest, rewritten).await;
+
+    let context = InstallContext {
+        window,
+        build_id,
+        client: &client,
+        extracted_version: &extracted_version,
+        compression: to_manifest.compression,
+        base_path,
+        workers: DEFAULT_CHUNK_WORKERS,
+        cancel: download_manager.cancellation_token(build_id).await,
+        origin: ChunkOrigin::Mirrors(download_manager.manifest_mirrors.clone()),
+        local_chunks,
+    };
+    let patched = patch_files(&context, &changed_files, &removed_files, download_manager).await?;
+
+    let full_install_bytes = to_manifest.size.max(0) as u64;
+    let rebuilt_bytes: u64 = changed_files
+        .iter()
+        .map(|chunked_file| chunked_file.file_size.max(0) as u64)
+        .sum();
+    Ok(UpdateResult {
+        success: true,
+        message: format!("Updated from {} to {}", from_version, to_version),
+        changed_files: changed_files.len(),
+        removed_files: removed_files.len(),
+        unchanged_files: to_manifest.chunks.len() - changed_files.len(),
+        downloaded_bytes: patched.downloaded,
+        full_install_bytes,
+        bytes_saved: full_install_bytes.saturating_sub(rebuilt_bytes.saturating_sub(patched.reused)),
+    })
+}
+
+#[command]
+pub async fn verify_build(
+    path: String,
+    version: String,
+    check_hashes: Option<bool>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<VerifyReport, LauncherError> {
+    let client = chunk_client()?;
+    let manifest = fetch_manifest(&client, &download_manager, &version).await?;
+
+    let files: Vec<&ChunkedFile> = manifest.chunks.iter().collect();
+    verify_files(Path::new(&path), &files, check_hashes.unwrap_or(true)).await
+        .map(|report| VerifyReport { version, ..report })
+        .map_err(|e| LauncherError::io(&path, e))
+}
+
+/// Verifies the install at `path` and downloads every file that is missing, truncated or
+/// corrupted. Progress is reported through the regular download events of `build_id`.
+#[command]
+pub async fn repair_build(
+    window: Window,
+    build_id: String,
+    path: String,
+    version: String,
+    download_manager: State<'_, DownloadManager>
+) -> Result<RepairResult, LauncherError> {
+    let job = JobRequest::Repair {
+        version: version.clone(),
+        path: path.clone(),
+    };
+
+    download_manager.run_job(
+        &window,
+        &build_id,
+        job,
+        run_repair(&window, &build_id, &path, version, &download_manager)
+    ).await
+}
+
+async fn run_repair(
+    window: &Window,
+    build_id: &str,
+    path: &str,
+    version: String,
+    download_manager: &DownloadManager
+) -> Result<RepairResult, LauncherError> {
+    let job = JobInfo {
+        build_id: build_id.to_string(),
+        kind: JobKind::Repair,
+        version: Some(version.clone()),
+        source: download_manager.manifest_source().await,
+    };
+    let result = apply_repair(window, build_id, path, &version, download_manager).await;
+    download_manager.finish_job(window, job, result).await
+}
+
+async fn apply_repair(
+    window: &Window,
+    build_id: &str,
+    path: &str,
+    version: &str,
+    download_manager: &DownloadManager
+) -> Result<RepairResult, LauncherError> {
+    download_manager.set_job_state(window, build_id, JobState::ResolvingManifest).await;
+    let client = chunk_client()?;
+    let manifest = fetch_manifest(&client, download_manager, version).await?;
+    let extracted_version = manifest_version(version).ok_or_else(|| LauncherError::InvalidVersion {
+        version: version.to_string(),
+    })?;
+
+    download_manager.set_job_state(window, build_id, JobState::Verifying).await;
+    let base_path = Path::new(path);
+    let files: Vec<&ChunkedFile> = manifest.chunks.iter().collect();
+    let report = verify_files(base_path, &files, true).await.map_err(|e| LauncherError::io(path, e))?;
+
+    let broken: std::collections::HashSet<&str> = report.missing_files
+        .iter()
+        .chain(&report.truncated_files)
+        .chain(&report.corrupted_files)
+        .map(|file| file.as_str())
+        .collect();
+    let broken_files: Vec<&ChunkedFile> = files
+        .into_iter()
+        .filter(|chunked_file| broken.contains(chunked_file.file.as_str()))
+        .collect();
+
+    if broken_files.is_empty() {
+        return Ok(RepairResult {
+            success: true,
+            message: "Build is intact".to_string(),
+            repaired_files: Vec::new(),
+            downloaded_bytes: 0,
+        });
+    }
+
+    let context = InstallContext {
+        window,
+        build_id,
+        client: &client,
+        extracted_version: &extracted_version,
+        compression: manifest.compression,
+        base_path,
+        workers: DEFAULT_CHUNK_WORKERS,
+        cancel: download_manager.cancellation_token(build_id).await,
+        origin: ChunkOrigin::Mirrors(download_manager.manifest_mirrors.clone()),
+        local_chunks: LocalChunks::default(),
+    };
+    let downloaded_bytes = patch_files(&context, &broken_files, &[], download_manager).await?.downloaded;
+
+    Ok(RepairResult {
+        success: true,
+        message: format!("Repaired {} files", broken_files.len()),
+        repaired_files: broken_files
+            .iter()
+            .map(|chunked_file| chunked_file.file.clone())
+            .collect(),
+        downloaded_bytes,
+    })
+}
+
+/// Checks every file of the manifest under `base_path`. Sizes are always compared, file
+/// hashes only when `check_hashes` is set and the manifest provides them.
+async fn verify_files(
+    base_path: &Path,
+    files: &[&ChunkedFile],
+    check_hashes: bool
+) -> io::Result<VerifyReport> {
+    let mut report = VerifyReport {
+        version: String::new(),
+        checked_files: files.len(),
+        missing_files: Vec::new(),
+        truncated_files: Vec::new(),
+        corrupted_files: Vec::new(),
+        healthy: true,
+    };
+
+    for chunked_file in files {
+        let file_path = base_path.join(&chunked_file.file);
+        let size = match tokio::fs::metadata(&file_path).await {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            Ok(_) => {
+                report.missing_files.push(chunked_file.file.clone());
+                continue;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                report.missing_files.push(chunked_file.file.clone());
+                continue;
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        let expected_size = chunked_file.file_size.max(0) as u64;
+        if size < expected_size {
+            report.truncated_files.push(chunked_file.file.clone());
+            continue;
+        }
+        if size > expected_size {
+            report.corrupted_files.push(chunked_file.file.clone());
+            continue;
+        }
+
+        let Some(expected_hash) = chunked_file.file_hash.as_ref().filter(|_| check_hashes) else {
+            continue;
+        };
+        if !hash_file(&file_path).await?.eq_ignore_ascii_case(expected_hash) {
+            report.corrupted_files.push(chunked_file.file.clone());
+        }
+    }
+
+    report.healthy =
+        report.missing_files.is_empty() &&
+        report.truncated_files.is_empty() &&
+        report.corrupted_files.is_empty();
+    Ok(report)
+}
+
+/// Downloads `files` into the existing install of `context` as a queued job and deletes
+/// `removed_files` once they are all in place. Unlike full installs these jobs are not
+/// journaled, so a pause or cancel simply ends them.
+async fn patch_files(
+    context: &InstallContext<'_>,
+    files: &[&ChunkedFile],
+    removed_files: &[&str],
+    download_manager: &DownloadManager
+) -> Result<PatchedBytes, LauncherError> {
+    let (window, build_id, base_path) = (context.window, context.build_id, context.base_path);
+    let total_size: i64 = files
+        .iter()
+        .map(|chunked_file| chunked_file.file_size)
+        .sum();
+
+    download_manager.set_job_state(window, build_id, JobState::Queued).await;
+    download_manager.enqueue(window, build_id, 0, false).await?;
+    // A job stopped while queued still ends like a running one
+    let slot = download_manager.wait_for_slot(window, build_id).await;
+    if slot.is_ok() {
+        download_manager.set_job_state(window, build_id, JobState::Downloading).await;
+    }
+
+    let mut journal = DownloadJournal::ephemeral(build_id, &base_path.to_string_lossy());
+    let mut result = match slot {
+        Ok(()) => install_files(context, files, total_size, &mut journal, download_manager).await,
+        Err(e) => Err(e),
+    };
+
+    if result.is_ok() {
+        download_manager.set_job_state(window, build_id, JobState::Finalizing).await;
+        for file in removed_files {
+            let file_path = match install_path(base_path, file).await {
+                Ok(file_path) => file_path,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            match tokio::fs::remove_file(&file_path).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    result = Err(LauncherError::io(&file_path, e));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    download_manager.release_slot(build_id).await;
+    download_manager.take_pause_request(build_id).await;
+    let cancelled = context.cancel.is_cancelled() && result.is_err();
+
+    match result {
+        Ok(()) => {
+            download_manager.report_progress(window, DownloadProgress {
+                build_id: build_id.to_string(),
+                percentage: 100.0,
+                downloaded_bytes: total_size as u64,
+                total_bytes: total_size as u64,
+                speed: 0.0,
+                eta: "0s".to_string(),
+            }).await;
+            let _ = window.emit("download:completed", build_id);
+            let stats = download_manager.job_stats(build_id).await;
+            Ok(PatchedBytes {
+                downloaded: stats.downloaded_bytes(),
+                reused: stats.reused_bytes(),
+            })
+        }
+        Err(e) => {
+            // Nothing can resume a patch job, so its partly written files are of no further use.
+            // Files are completed in order and every worker can be a chunk ahead of the file in
+            // progress, so only the next few files can have a .part file.
+            let in_progress = files
+                .iter()
+                .filter(|chunked_file| !journal.is_file_completed(&chunked_file.file))
+                .take(DEFAULT_CHUNK_WORKERS + 1);
+            for chunked_file in in_progress {
+                if let Ok(file_path) = install_path(base_path, &chunked_file.file).await {
+                    let part_path = format!("{}.part", file_path.to_string_lossy());
+                    remove_temp_path(Path::new(&part_path)).await;
+                }
+            }
+            Err(if cancelled { LauncherError::Cancelled } else { e })
+        }
+    }
+}
+
+/// Where the files of a patch job came from.
+struct PatchedBytes {
+    /// Bytes received from the network.
+    downloaded: u64,
+    /// Decompressed bytes copied from the install or the chunk cache.
+    reused: u64,
+}
+
+/// Where the manifest file `file` goes below `base_path`, refusing paths that would end up
+/// outside of it.
+async fn install_path(base_path: &Path, file: &str) -> Result<PathBuf, LauncherError> {
+    contained_path(base_path, file).await.map_err(|e| LauncherError::io(base_path.join(file), e))
+}
+
+fn chunk_client() -> reqwest::Result<Client> {
+    Client::builder()
+        .pool_max_idle_per_host(20)
+        .pool_idle_timeout(std::time::Duration::from_secs(30))
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .connect_timeout(std::time::Duration::from_secs(10))
+        .build()
+}
+
+fn manifest_version(version: &str) -> Option<String> {
+    let re = Regex::new(r"Release-(\d+\.\d+)").ok()?;
+    Some(re.captures(version)?.get(1)?.as_str().to_string())
+}
+
+/// Installs the version `journal` describes into its install path, continuing wherever the
+/// journal left off.
+async fn download_manifest(
+    window: &Window,
+    source: &ChunkSource,
+    journal: &mut DownloadJournal,
+    download_manager: &State<'_, DownloadManager>
+) -> Result<(), LauncherError> {
+    let build_id = journal.build_id.clone();
+    let version = journal.version.clone();
+    let install_path = PathBuf::from(&journal.install_path);
+    let client = chunk_client()?;
+
+    download_manager.set_job_state(window, &build_id, JobState::ResolvingManifest).await;
+    let manifest = fetch_source_manifest(&client, download_manager, &version, source).await?;
+    let extracted_version = manifest_version(&version).ok_or_else(|| LauncherError::InvalidVersion {
+        version: version.clone(),
+    })?;
+    let origin = match source {
+        ChunkSource::Mirrors => ChunkOrigin::Mirrors(download_manager.manifest_mirrors.clone()),
+        ChunkSource::Http(base_url) => ChunkOrigin::Mirrors(Arc::new(MirrorSet::new([base_url.as_str()]))),
+        ChunkSource::Directory(root) => ChunkOrigin::Directory(version_dir(root, &extracted_version).await),
+    };
+    download_manager.set_job_state(window, &build_id, JobState::Downloading).await;
+
+    let files: Vec<&ChunkedFile> = manifest.chunks.iter().collect();
+    let context = InstallContext {
+        window,
+        build_id: &build_id,
+        client: &client,
+        extracted_version: &extracted_version,
+        compression: manifest.compression,
+        base_path: &install_path,
+        workers: journal.max_workers.unwrap_or(DEFAULT_CHUNK_WORKERS),
+        cancel: download_manager.cancellation_token(&build_id).await,
+        origin,
+        local_chunks: LocalChunks::default(),
+    };
+    install_files(&context, &files, manifest.size, journal, download_manager).await?;
+
+    let total_size = manifest.size;
+
+    download_manager.report_progress(window, DownloadProgress {
+        build_id: build_id.clone(),
+        percentage: 100.0,
+        downloaded_bytes: total_size as u64,
+        total_bytes: total_size as u64,
+        speed: 0.0,
+        eta: "0s".to_string(),
+    }).await;
+
+    let _ = window.emit("download:completed", build_id);
+
+    Ok(())
+}
+
+struct InstallContext<'a> {
+    window: &'a Window,
+    build_id: &'a str,
+    client: &'a Client,
+    extracted_version: &'a str,
+    /// Compression of chunks the manifest does not list one for.
+    compression: ChunkCompression,
+    base_path: &'a Path,
+    workers: usize,
+    cancel: CancellationToken,
+    origin: ChunkOrigin,
+    /// Chunks already on disk that are copied instead of downloaded.
+    local_chunks: LocalChunks,
+}
+
+/// Where the chunks of an install are read from.
+#[derive(Clone)]
+enum ChunkOrigin {
+    Mirrors(Arc<MirrorSet>),
+    /// The folder holding the `.chunk` files of the version being installed.
+    Directory(PathBuf),
+}
+
+/// Resolves to the offset a chunk starts at in its `.part` file, or `None` when the chunk
+/// before it, whose end it was waiting for, failed.
+type ChunkStart = Shared<BoxFuture<'static, Option<u64>>>;
+
+fn chunk_starts_at(offset: u64) -> ChunkStart {
+    futures::future::ready(Some(offset)).boxed().shared()
+}
+
+/// Where a worker writes a chunk to.
+#[derive(Clone)]
+struct ChunkTarget {
+    part_path: PathBuf,
+    start: ChunkStart,
+}
+
+impl ChunkTarget {
+    /// Waits for the offset to write at. When the chunk before this one failed, the job is
+    /// over and reports that failure, so this one simply stops like a cancelled chunk.
+    async fn offset(&self) -> Result<u64, LauncherError> {
+        self.start.clone().await.ok_or(LauncherError::Cancelled)
+    }
+}
+
+/// A chunk a worker has written into its `.part` file.
+struct WrittenChunk {
+    offset: u64,
+    size: u64,
+}
+
+/// Downloads and assembles `files` under `context.base_path`, skipping whatever `journal`
+/// already records as done. `total_size` is only used for progress reporting.
+///
+/// Chunks are fetched in parallel and every worker decodes its chunk straight into the
+/// `.part` file at the chunk's offset, so memory use does not depend on the chunk size.
+/// Manifests without chunk sizes do not say where a chunk starts until the one before it is
+/// written, so such a chunk is requested right away but its body is only read after that.
+async fn install_files(
+    context: &InstallContext<'_>,
+    files: &[&ChunkedFile],
+    total_size: i64,
+    journal: &mut DownloadJournal,
+    download_manager: &DownloadManager
+) -> Result<(), LauncherError> {
+    let mut completed_size = journal.completed_bytes as i64;
     let mut last_update = std::time::Instant::now();
 
+    let base_path = context.base_path;
+    if let Some(parent) = base_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    // A partial file can only be continued if its chunks still match the manifest and the
+    // .part file on disk holds at least as many bytes as the journal recorded
+    if let Some(current) = journal.current_file.clone() {
+        let part_intact = match install_path(base_path, &current.file).await {
+            Ok(file_path) => {
+                let temp_file_path = format!("{}.part", file_path.to_string_lossy());
+                tokio::fs::metadata(&temp_file_path).await.is_ok_and(|metadata| metadata.len() >= current.bytes)
+            }
+            Err(_) => false,
+        };
+        let chunks_match = files
+            .iter()
+            .find(|chunked_file| chunked_file.file == current.file)
+            .is_some_and(|chunked_file| chunked_file.chunks_ids.starts_with(&current.chunks));
+
+        if !chunks_match || !part_intact {
+            journal.discard_current_file();
+        }
+    }
+    journal.save()?;
+
+    let resumed_chunks = |journal: &DownloadJournal, file: &str| {
+        journal.current_file
+            .as_ref()
+            .filter(|current| current.file == file)
+            .map(|current| (current.chunks.len(), current.bytes))
+    };
+
+    let mut file_paths = std::collections::HashMap::new();
+    let mut pending_chunks = Vec::new();
+    for chunked_file in files.iter().filter(|chunked_file| !journal.is_file_completed(&chunked_file.file)) {
+        let file_path = install_path(base_path, &chunked_file.file).await?;
+        let part_path = PathBuf::from(format!("{}.part", file_path.to_string_lossy()));
+        let (skip, bytes) = resumed_chunks(journal, &chunked_file.file).unwrap_or((0, 0));
+
+        let mut previous_end = chunk_starts_at(bytes);
+        for index in skip..chunked_file.chunks_ids.len() {
+            let chunk = chunked_file.chunk(index, context.compression);
+            let start = match chunk.offset {
+                Some(offset) => chunk_starts_at(offset),
+                None => previous_end.clone(),
+            };
+            let (end_sender, end) = tokio::sync::oneshot::channel::<u64>();
+            previous_end = end.map(Result::ok).boxed().shared();
+            let local = context.local_chunks.find(&chunked_file.file, &chunk);
+            pending_chunks.push((chunk, ChunkTarget { part_path: part_path.clone(), start }, end_sender, local));
+        }
+        file_paths.insert(chunked_file.file.as_str(), file_path);
+    }
+
+    let fetcher = ChunkFetcher {
+        client: context.client.clone(),
+        origin: context.origin.clone(),
+        build_id: context.build_id.to_string(),
+        bandwidth: download_manager.bandwidth.clone(),
+        stats: download_manager.job_stats(context.build_id).await,
+        cancel: context.cancel.clone(),
+    };
+    let mut chunk_stream = futures::stream
+        ::iter(pending_chunks)
+        .map(|(chunk, target, end_sender, local)| {
+            let chunk_path = format!("{}/{}.chunk", context.extracted_version, chunk.id);
+            let cache = download_manager.chunk_cache.clone();
+            let cache_key = ChunkKey::new(context.extracted_version, chunk.id, chunk.hash.as_deref(), chunk.size);
+            // Chunks read from a local source are already on disk, caching them gains nothing
+            let caches = matches!(context.origin, ChunkOrigin::Mirrors(_));
+            let fetcher = fetcher.clone();
+            let (task, handle) = (async move {
+                let mut reused = None;
+                if let Some(local) = local {
+                    let offset = target.offset().await?;
+                    reused = local
+                        .copy_to(&chunk, &target.part_path, offset).await
+                        .map(|size| WrittenChunk { offset, size });
+                }
+                if reused.is_none() && cache.contains(&cache_key).await {
+                    let offset = target.offset().await?;
+                    reused = cache
+                        .get(&cache_key, &target.part_path, offset).await
+                        .map(|size| WrittenChunk { offset, size });
+                }
+                let written = match reused {
+                    Some(written) => {
+                        fetcher.stats.add_reused_bytes(written.size);
+                        written
+                    }
+                    None => {
+                        // Dropping the fetch aborts its request right away instead of after a timeout
+                        let written = tokio::select! {
+                            written = fetcher.fetch(&chunk_path, &chunk, &target) => written?,
+                            _ = fetcher.cancel.cancelled() => {
+                                return Err(LauncherError::Cancelled);
+                            }
+                        };
+                        // Failing to cache a chunk only costs a download next time
+                        if caches {
+                            let _ = cache.insert(&cache_key, &target.part_path, written.offset, written.size).await;
+                        }
+                        written
+                    }
+                };
+                let _ = end_sender.send(written.offset + written.size);
+                Ok::<_, LauncherError>(written)
+            }).remote_handle();
+            tokio::spawn(task);
+            handle
+        })
+        .buffered(context.workers.clamp(1, MAX_CHUNK_WORKERS));
+
+    let mut last_journal_save = std::time::Instant::now();
+
+    for chunked_file in files {
+        let Some(file_path) = file_paths.get(chunked_file.file.as_str()) else {
+            continue;
+        };
+        let temp_file_path = format!("{}.part", file_path.to_string_lossy());
+        let part_error = |e| LauncherError::io(&temp_file_path, e);
+
+        let (skip, mut file_size) = resumed_chunks(journal, &chunked_file.file).unwrap_or((0, 0));
+
+        for chunk_id in chunked_file.chunks_ids.iter().skip(skip) {
+            let next_chunk = tokio::select! {
+                biased;
+                _ = context.cancel.cancelled() => {
+                    // Whether this is a pause or a cancel is decided by the caller, which also
+                    // removes the partial install when the download was cancelled
+                    journal.save()?;
+                    return Err(LauncherError::Cancelled);
+                }
+                next_chunk = chunk_stream.next() => next_chunk,
+            };
+
+            let chunk = match next_chunk {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    journal.save()?;
+                    return Err(e.with_file(&chunked_file.file));
+                }
+                None => {
+                    journal.save()?;
+                    return Err(LauncherError::Chunk {
+                        message: format!("Chunk {} was never delivered", chunk_id),
+                        file: Some(chunked_file.file.clone()),
+                        chunk_id: *chunk_id,
+                        status: None,
+                    });
+                }
+            };
+
+            // Workers write their chunks before handing them over, so the journal never runs
+            // ahead of the .part file. It may lag behind, chunks past it are written again
+            journal.record_chunk(&chunked_file.file, *chunk_id, chunk.size);
+            file_size = chunk.offset + chunk.size;
+
+            if last_journal_save.elapsed().as_millis() > (JOURNAL_SAVE_INTERVAL_MS as u128) {
+                journal.save()?;
+                last_journal_save = std::time::Instant::now();
+            }
+
+            completed_size += chunk.size as i64;
+            let percentage = ((completed_size as f64) / (total_size as f64)) * 100.0;
+
+            let speed = download_manager.update_speed_data(context.build_id, completed_size as u64).await;
+            let remaining_bytes = total_size - completed_size;
+            let eta_seconds = if speed > 0.0 {
+                (remaining_bytes as f64) / speed
+            } else {
+                f64::INFINITY
+            };
+            let eta = format_time(eta_seconds);
+
+            if last_update.elapsed().as_millis() > (UPDATE_INTERVAL_MS as u128) {
+                download_manager.report_progress(context.window, DownloadProgress {
+                    build_id: context.build_id.to_string(),
+                    percentage,
+                    downloaded_bytes: completed_size as u64,
+                    total_bytes: total_size as u64,
+                    speed,
+                    eta,
+                }).await;
+
+                last_update = std::time::Instant::now();
+            }
+        }
+
+        // Checked whether or not there is a file hash, a file that came out at another size
+        // than the manifest gives must not replace the installed one
+        if file_size as i64 != chunked_file.file_size {
+            let _ = tokio::fs::remove_file(&temp_file_path).await;
+            journal.discard_current_file();
+            journal.save()?;
+            return Err(LauncherError::Integrity {
+                message: format!("File size mismatch: expected {}, got {}", chunked_file.file_size, file_size),
+                file: Some(chunked_file.file.clone()),
+                chunk_id: None,
+            });
+        }
+
+        // A file without chunks has no .part yet, and an earlier attempt may have left bytes
+        // past the end of this one
+        if let Some(parent) = file_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let output_file = AsyncOpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&temp_file_path).await
+            .map_err(part_error)?;
+        output_file.set_len(file_size).await.map_err(part_error)?;
+        drop(output_file);
+
+        if let Some(expected_hash) = &chunked_file.file_hash {
+            let actual_hash = hash_file(Path::new(&temp_file_path)).await?;
+            if !actual_hash.eq_ignore_ascii_case(expected_hash) {
+                let _ = tokio::fs::remove_file(&temp_file_path).await;
+                journal.discard_current_file();
+                journal.save()?;
+                return Err(LauncherError::Integrity {
+                    message: format!("File hash mismatch: expected {}, got {}", expected_hash, actual_hash),
+                    file: Some(chunked_file.file.clone()),
+                    chunk_id: None,
+                });
+            }
+        }
+
+        if file_path.exists() {
+            tokio::fs::remove_file(file_path).await?;
+        }
+
+        tokio::fs::rename(&temp_file_path, file_path).await.map_err(|e| LauncherError::io(file_path, e))?;
+
+        journal.complete_file(&chunked_file.file);
+        journal.save()?;
+    }
+
+    Ok(())
+}
+
+/// What the chunk workers of one install share. It is owned, so every worker can take a
+/// copy into its task.
+#[derive(Clone)]
+struct ChunkFetcher {
+    client: Client,
+    origin: ChunkOrigin,
+    build_id: String,
+    bandwidth: Arc<BandwidthLimiter>,
+    stats: Arc<JobStats>,
+    cancel: CancellationToken,
+}
+
+impl ChunkFetcher {
+    /// Writes `chunk` into `target`, from the healthiest mirror that serves it or from the
+    /// local source folder.
+    async fn fetch(
+        &self,
+        chunk_path: &str,
+        chunk: &ChunkRef,
+        target: &ChunkTarget
+    ) -> Result<WrittenChunk, LauncherError> {
+        let stats = &self.stats;
+        let chunk_id = chunk.id;
+        let chunk_error = |message: String, status: Option<u16>| LauncherError::Chunk {
+            message,
+            file: None,
+            chunk_id,
+            status,
+        };
+        let record_attempts = |attempts: u64, succeeded: bool| {
+            stats.add_retries(attempts.saturating_sub(1));
+            if attempts > 1 || !succeeded {
+                stats.record_failed_chunk(chunk_id);
+            }
+        };
+
+        let mirrors = match &self.origin {
+            ChunkOrigin::Mirrors(mirrors) => mirrors,
+            // A local source either has the chunk or it does not, so it is not retried
+            ChunkOrigin::Directory(dir) => {
+                let result = read_local_chunk(dir, chunk, target, &self.cancel).await;
+                record_attempts(1, result.is_ok());
+                return result;
+            }
+        };
+
+        let mut last_error = chunk_error(format!("No mirrors available for chunk {}", chunk_id), None);
+        let mut attempts = 0;
+
+        for base_url in mirrors.ordered().await {
+            let chunk_url = format!("{}/{}", base_url, chunk_path);
+            let mut retries = 0;
+
+            loop {
+                attempts += 1;
+                last_error = match self.client.get(&chunk_url).send().await {
+                    Ok(response) => {
+                        if response.status().is_success() {
+                            match self.stream(response, chunk, target).await {
+                                Ok(written) => {
+                                    mirrors.record_success(&base_url).await;
+                                    record_attempts(attempts, true);
+                                    return Ok(written);
+                                }
+                                // The .part file could not be written or the job stopped, no mirror is
+                                // going to fix that
+                                Err(
+                                    e @ (
+                                        | LauncherError::Io { .. }
+                                        | LauncherError::DiskFull { .. }
+                                        | LauncherError::Cancelled
+                                    ),
+                                ) => {
+                                    record_attempts(attempts, false);
+                                    return Err(e);
+                                }
+                                // A corrupted or mis-served chunk is fetched again like any other failure
+                                Err(e) => e,
+                            }
+                        } else if response.status().is_server_error() {
+                            chunk_error(
+                                format!("Failed to download chunk {}: HTTP {}", chunk_id, response.status()),
+                                Some(response.status().as_u16())
+                            )
+                        } else {
+                            // Client error, don't retry on this mirror but another one may have the chunk
+                            last_error = chunk_error(
+                                format!("Failed to download chunk {}: HTTP {}", chunk_id, response.status()),
+                                Some(response.status().as_u16())
+                            );
+                            break;
+                        }
+                    }
+                    Err(e) => chunk_error(format!("Network error downloading chunk {}: {}", chunk_id, e), None),
+                };
+
+                retries += 1;
+                if retries >= MAX_RETRIES {
+                    break;
+                }
+
+                tokio::time::sleep(Duration::from_millis(RETRY_DELAY_MS * (retries as u64))).await;
+            }
+
+            mirrors.record_failure(&base_url).await;
+        }
+
+        record_attempts(attempts, false);
+        Err(last_error)
+    }
+
+    /// Streams a chunk from `response` into its `.part` file once the offset of `target` is
+    /// known. Only a buffer's worth of the chunk is ever held in memory.
+    async fn stream(
+        &self,
+        response: reqwest::Response,
+        chunk: &ChunkRef,
+        target: &ChunkTarget
+    ) -> Result<WrittenChunk, LauncherError> {
+        let offset = target.offset().await?;
+        let (build_id, bandwidth, stats) = (self.build_id.clone(), self.bandwidth.clone(), self.stats.clone());
+        let body = response
+            .bytes_stream()
+            .then(move |piece| {
+                let (build_id, bandwidth, stats) = (build_id.clone(), bandwidth.clone(), stats.clone());
+                async move {
+                    let piece = piece.map_err(io::Error::other)?;
+                    bandwidth.acquire(&build_id, piece.len() as u64).await;
+                    stats.add_bytes(piece.len() as u64);
+                    Ok::<_, io::Error>(piece)
+                }
+            })
+            // The body is read on a blocking thread that outlives a dropped fetch, so it is ended
+            // here as soon as the job stops, whether it waits for data or for bandwidth
+            .take_until(self.cancel.clone().cancelled_owned());
+        let body = SyncIoBridge::new(StreamReader::new(Box::pin(body)));
+
+        let size = decode_chunk(body, chunk, &target.part_path, offset, &self.cancel).await?;
+        Ok(WrittenChunk { offset, size })
+    }
+}
+
+/// Runs [`write_chunk`] on a blocking thread, decompressing and hashing are too much work
+/// for the runtime workers.
+async fn decode_chunk(
+    body: impl Read + Send + 'static,
+    chunk: &ChunkRef,
+    part_path: &Path,
+    offset: u64,
+    cancel: &CancellationToken
+) -> Result<u64, LauncherError> {
+    let (chunk, part_path, cancel) = (chunk.clone(), part_path.to_path_buf(), cancel.clone());
+    tokio::task
+        ::spawn_blocking(move || write_chunk(body, &chunk, &part_path, offset, &cancel)).await
+        .map_err(|e| LauncherError::other(format!("Chunk decoder stopped: {}", e)))?
+}
+
+/// Decodes a chunk from `body` into `part_path` at `offset`, checking its size and hash on
+/// the way, and returns the decompressed size.
+fn write_chunk(
+    body: impl Read,
+    chunk: &ChunkRef,
+    part_path: &Path,
+    offset: u64,
+    cancel: &CancellationToken
+) -> Result<u64, LauncherError> {
+    let chunk_id = chunk.id;
+    let expected_hash = chunk.hash.as_deref();
+    let mut decoder: Box<dyn Read> = match chunk.compression {
+        ChunkCompression::Gzip => Box::new(GzDecoder::new(body)),
+        ChunkCompression::Zstd =>
+            Box::new(ZstdDecoder::new(body).map_err(|e| chunk_read_error(e, chunk_id))?),
+        ChunkCompression::None => Box::new(body),
+    };
+
+    // A stopped job removes the .part files it leaves behind, so they are not brought back
+    if cancel.is_cancelled() {
+        return Err(LauncherError::Cancelled);
+    }
+    let part_error = |e| LauncherError::io(part_path, e);
+    if let Some(parent) = part_path.parent() {
+        fs::create_dir_all(parent).map_err(part_error)?;
+    }
+    // Other workers write the rest of the file at the same time, so it is never truncated here
+    let mut part_file = fs::OpenOptions
+        ::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(part_path)
+        .map_err(part_error)?;
+    part_file.seek(SeekFrom::Start(offset)).map_err(part_error)?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; CHUNK_BUFFER_SIZE];
+    let mut size = 0u64;
+
+    loop {
+        let read = decoder.read(&mut buffer).map_err(|e| chunk_read_error(e, chunk_id))?;
+        if read == 0 {
+            break;
+        }
+        if expected_hash.is_some() {
+            hasher.update(&buffer[..read]);
+        }
+        part_file.write_all(&buffer[..read]).map_err(part_error)?;
+        size += read as u64;
+    }
+    part_file.flush().map_err(part_error)?;
+
+    // A stopped job ends the body early, which must not pass for a complete chunk
+    if cancel.is_cancelled() {
+        return Err(LauncherError::Cancelled);
+    }
+
+    if let Some(expected_size) = chunk.size.filter(|expected_size| *expected_size != size) {
+        return Err(LauncherError::Integrity {
+            message: format!("Chunk {} is {} bytes, expected {}", chunk_id, size, expected_size),
+            file: None,
+            chunk_id: Some(chunk_id),
+        });
+    }
+
+    if let Some(expected_hash) = expected_hash {
+        let actual_hash = format!("{:x}", hasher.finalize());
+        if !actual_hash.eq_ignore_ascii_case(expected_hash) {
+            return Err(LauncherError::Integrity {
+                message: format!(
+                    "Chunk {} hash mismatch: expected {}, got {}",
+                    chunk_id,
+                    expected_hash,
+                    actual_hash
+                ),
+                file: None,
+                chunk_id: Some(chunk_id),
+            });
+        }
+    }
+
+    Ok(size)
+}
+
+/// Reads a chunk from the source folder `dir` into its `.part` file with the same checks as
+/// a downloaded one.
+async fn read_local_chunk(
+    dir: &Path,
+    chunk: &ChunkRef,
+    target: &ChunkTarget,
+    cancel: &CancellationToken
+) -> Result<WrittenChunk, LauncherError> {
+    let chunk_path = dir.join(format!("{}.chunk", chunk.id));
+    let file = AsyncFile::open(&chunk_path).await.map_err(|e| LauncherError::Chunk {
+        message: format!("Failed to read chunk {} from {}: {}", chunk.id, chunk_path.display(), e),
+        file: None,
+        chunk_id: chunk.id,
+        status: None,
+    })?;
+    let offset = target.offset().await?;
+    let body = io::BufReader::with_capacity(CHUNK_BUFFER_SIZE, file.into_std().await);
+
+    let size = decode_chunk(body, chunk, &target.part_path, offset, cancel).await?;
+    Ok(WrittenChunk { offset, size })
+}
+
+/// Tells a connection that failed halfway through a chunk apart from data the decoder rejected.
+fn chunk_read_error(error: io::Error, chunk_id: i32) -> LauncherError {
+    if error.get_ref().is_some_and(|inner| inner.is::<reqwest::Error>()) {
+        LauncherError::Chunk {
+            message: format!("Failed to download chunk data: {}", error),
+            file: None,
+            chunk_id,
+            status: None,
+        }
+    } else {
+        LauncherError::Integrity {
+            message: format!("Failed to decompress chunk {}: {}", chunk_id, error),
+            file: None,
+            chunk_id: Some(chunk_id),
+        }
+    }
+}
+
+/// Identifies the version of a remote file that a partial download belongs to. It is sent
+/// back in `If-Range` so a server only continues a transfer when the file has not changed.
+#[derive(Clone)]
+struct RangeValidator {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    total_bytes: Option<u64>,
+    /// The byte ranges of a segmented download and how far each of them got.
+    segments: Option<Arc<Vec<Segment>>>,
+}
+
+impl RangeValidator {
+    fn from_response(response: &reqwest::Response, total_bytes: Option<u64>) -> Self {
+        let header = |name: reqwest::header::HeaderName| {
+            response.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        Self {
+            // Weak entity tags are not allowed in If-Range
+            etag: header(reqwest::header::ETAG).filter(|etag| !etag.starts_with("W/")),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            total_bytes,
+            segments: None,
+        }
+    }
+
+    fn if_range(&self) -> Option<&str> {
+        self.etag.as_deref().or(self.last_modified.as_deref())
+    }
+}
+
+/// One byte range of a segmented download, `end` exclusive. `written` counts the bytes at
+/// the start of the range that are already on disk.
+struct Segment {
+    start: u64,
+    end: u64,
+    written: AtomicU64,
+}
+
+impl Segment {
+    /// Splits a file of `total_bytes` into one range per connection of at least
+    /// `MIN_SEGMENT_BYTES` each. Only the last range can fall short of that, by a few bytes
+    /// of rounding or because the file itself is smaller.
+    fn split(total_bytes: u64) -> Vec<Segment> {
+        let count = (total_bytes / MIN_SEGMENT_BYTES).clamp(1, SEGMENT_CONNECTIONS);
+        let size = total_bytes.div_ceil(count);
+
+        (0..count)
+            .map(|index| Segment {
+                start: index * size,
+                end: ((index + 1) * size).min(total_bytes),
+                written: AtomicU64::new(0),
+            })
+            .collect()
+    }
+
+    fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a segment stopped before its range was complete.
+enum SegmentError {
+    /// The server answered a range request with something other than that range, so the
+    /// file has to be fetched in one piece.
+    RangesIgnored,
+    Failed(LauncherError),
+}
+
+impl From<LauncherError> for SegmentError {
+    fn from(error: LauncherError) -> Self {
+        Self::Failed(error)
+    }
+}
+
+/// Parses a `Content-Range` header into the first byte position and the total size, when
+/// the server gave one. Unsatisfied ranges (`bytes */<total>`) have no first byte.
+fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (span, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let start = span.split_once('-').and_then(|(start, _)| start.trim().parse().ok());
+    Some((start, total.trim().parse().ok()))
+}
+
+fn content_range(response: &reqwest::Response) -> Option<(Option<u64>, Option<u64>)> {
+    response.headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_content_range)
+}
+
+/// Downloads `url` to `destination`. Interrupted transfers are continued with a `Range`
+/// request guarded by `If-Range`, and whenever the server answers with the whole file
+/// instead the partial file is thrown away rather than stitched together with new data.
+/// With `resume` set, a partial file left by a paused run of the same build is continued.
+/// `required` files retry for longer, see [`transfer_retries`].
+async fn download_file(
+    window: Window,
+    build_id: String,
+    url: &str,
+    destination: &str,
+    resume: bool,
+    required: bool,
+    download_manager: &State<'_, DownloadManager>
+) -> Result<(), LauncherError> {
     let client = Client::builder()
         .pool_max_idle_per_host(20)
         .pool_idle_timeout(std::time::Duration::from_secs(30))
-        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
         .connect_timeout(std::time::Duration::from_secs(10))
         .build()?;
 
-    let re = Regex::new(r"Release-(\d+\.\d+)")?;
-    let extracted_version = match re.captures(version) {
-        Some(caps) => caps.get(1).unwrap().as_str().to_string(),
-        None => {
-            return Err("Version extraction failed".into());
-        }
+    let stats = download_manager.job_stats(&build_id).await;
+    let cancel = download_manager.cancellation_token(&build_id).await;
+
+    // Without the validator of the paused run there is no telling which version of the file
+    // the bytes on disk belong to, so they are only kept when one was stored
+    let stored_validator = download_manager.range_validators.lock().await.remove(&build_id);
+    let validator = stored_validator.filter(|_| resume);
+
+    let segmented = match &validator {
+        Some(validator) => validator.segments.is_some().then(|| validator.clone()),
+        None =>
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    return Err(LauncherError::Cancelled);
+                }
+                segmented = probe_segments(&client, url) => segmented,
+            }
     };
 
-    let base_path = Path::new(install_path);
-    if let Some(parent) = base_path.parent() {
-        tokio::fs::create_dir_all(parent).await?;
+    let transfer = FileTransfer {
+        window: &window,
+        build_id: &build_id,
+        client: &client,
+        url,
+        destination,
+        cancel: &cancel,
+        stats: &stats,
+        max_retries: transfer_retries(required),
+        download_manager,
+    };
+    let mut total_bytes = None;
+    let mut completed = false;
+    if let Some(segmented) = segmented {
+        total_bytes = segmented.total_bytes;
+        completed = download_segments(&transfer, segmented).await?;
+    }
+    if !completed {
+        // Segment progress means nothing to a single stream, which starts over instead
+        let validator = validator.filter(|validator| validator.segments.is_none());
+        total_bytes = stream_file(&transfer, validator).await?;
+    }
+
+    let file_error = |e| LauncherError::io(destination, e);
+    let file_size = tokio::fs::metadata(destination).await.map_err(file_error)?.len();
+    if file_size == 0 {
+        let _ = tokio::fs::remove_file(destination).await;
+        return Err(LauncherError::Integrity {
+            message: "Downloaded file is empty. The download may have failed.".into(),
+            file: None,
+            chunk_id: None,
+        });
+    }
+
+    if let Some(total) = total_bytes.filter(|total| *total != file_size) {
+        let _ = tokio::fs::remove_file(destination).await;
+        return Err(LauncherError::Integrity {
+            message: format!(
+                "Downloaded file size ({}) doesn't match expected size ({}). The download may be incomplete.",
+                file_size,
+                total
+            ),
+            file: None,
+            chunk_id: None,
+        });
     }
 
-    for chunked_file in manifest.chunks {
-        let file_path = base_path.join(&chunked_file.file);
-        if let Some(parent) = file_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
-        }
+    let _ = window.emit("download:completed", build_id);
+
+    Ok(())
+}
+
+/// A URL being downloaded to `destination` by [`download_file`], in one stream or in
+/// parallel segments.
+#[derive(Clone, Copy)]
+struct FileTransfer<'a> {
+    window: &'a Window,
+    build_id: &'a str,
+    client: &'a Client,
+    url: &'a str,
+    destination: &'a str,
+    cancel: &'a CancellationToken,
+    stats: &'a JobStats,
+    /// Attempts in a row without progress before the transfer gives up.
+    max_retries: usize,
+    download_manager: &'a DownloadManager,
+}
+
+/// How many attempts in a row may fail without progress before a file transfer gives up.
+/// A build download that gives up can be resumed later, but the game cannot launch without
+/// a required file and nothing resumes one, so those keep trying through a few minutes of
+/// outage.
+fn transfer_retries(required: bool) -> usize {
+    if required { REQUIRED_FILE_RETRIES } else { MAX_RETRIES }
+}
+
+/// How long a file transfer waits before retry number `retries`.
+fn retry_delay(retries: usize) -> Duration {
+    Duration::from_millis((RETRY_DELAY_MS * (retries as u64)).min(MAX_RETRY_DELAY_MS))
+}
+
+/// Streams the URL of `transfer` over one connection, continuing the partial file when
+/// `validator` belongs to it. Returns the size of the file when the server reported one.
+async fn stream_file(
+    transfer: &FileTransfer<'_>,
+    mut validator: Option<RangeValidator>
+) -> Result<Option<u64>, LauncherError> {
+    let FileTransfer { window, build_id, client, url, destination, cancel, stats, max_retries, download_manager } =
+        *transfer;
+    let mut written_bytes = match validator {
+        Some(_) =>
+            tokio::fs
+                ::metadata(destination).await
+                .map(|metadata| metadata.len())
+                .unwrap_or(0),
+        None => 0,
+    };
+    let mut total_bytes = validator.as_ref().and_then(|validator| validator.total_bytes);
+
+    let file_error = |e| LauncherError::io(destination, e);
+    let mut file = AsyncOpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(destination).await
+        .map_err(file_error)?;
+    file.set_len(written_bytes).await.map_err(file_error)?;
+    file.seek(SeekFrom::End(0)).await.map_err(file_error)?;
 
-        let temp_file_path = format!("{}.part", file_path.to_string_lossy());
+    let mut retries = 0;
+    let mut first_attempt = true;
+    let mut last_update = std::time::Instant::now();
 
-        let mut output_file = AsyncFile::create(&temp_file_path).await?;
+    loop {
+        if !first_attempt {
+            stats.add_retries(1);
+        }
+        first_attempt = false;
+        if retries > 0 {
+            tokio::time::sleep(retry_delay(retries)).await;
+        }
 
-        for chunk_id in &chunked_file.chunks_ids {
-            {
-                let active_downloads = download_manager.active_downloads.lock().await;
-                if !active_downloads.contains(&build_id) {
-                    let _ = tokio::fs::remove_file(&temp_file_path).await;
-                    return Err("Download cancelled".into());
-                }
+        let mut request = client.get(url);
+        if written_bytes > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", written_bytes));
+            if let Some(if_range) = validator.as_ref().and_then(RangeValidator::if_range) {
+                request = request.header(reqwest::header::IF_RANGE, if_range);
             }
+        }
 
-            let chunk_url = format!("{}/{}/{}.chunk", BASE_URL, extracted_version, chunk_id);
+        let sent = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                file.flush().await.map_err(file_error)?;
+                download_manager.keep_range_validator(build_id, validator).await;
+                return Err(LauncherError::Cancelled);
+            }
+            sent = request.send() => sent,
+        };
+        let response = match sent {
+            Ok(response) => response,
+            Err(e) => {
+                retries += 1;
+                if retries >= max_retries {
+                    return Err(LauncherError::Network {
+                        message: format!("Failed to download after {} attempts: {}", retries, e),
+                        url: Some(url.to_string()),
+                    });
+                }
+                continue;
+            }
+        };
 
-            let mut retries = 0;
-            let mut chunk_data = None;
+        let status = response.status();
+        if status == reqwest::StatusCode::PARTIAL_CONTENT {
+            let (start, total) = content_range(&response).unwrap_or((None, None));
+            let same_total = match (total, total_bytes) {
+                (Some(total), Some(expected)) => total == expected,
+                _ => true,
+            };
 
-            while retries < MAX_RETRIES && chunk_data.is_none() {
-                match client.get(&chunk_url).send().await {
-                    Ok(response) => {
-                        if response.status().is_success() {
-                            match response.bytes().await {
-                                Ok(data) => {
-                                    chunk_data = Some(data);
-                                }
-                                Err(e) => {
-                                    retries += 1;
-                                    if retries >= MAX_RETRIES {
-                                        let _ = tokio::fs::remove_file(&temp_file_path).await;
-                                        return Err(
-                                            format!("Failed to download chunk data: {}", e).into()
-                                        );
-                                    }
-                                    tokio::time::sleep(
-                                        Duration::from_millis(RETRY_DELAY_MS * (retries as u64))
-                                    ).await;
-                                }
-                            }
-                        } else if response.status().is_server_error() {
-                            retries += 1;
-                            if retries >= MAX_RETRIES {
-                                let _ = tokio::fs::remove_file(&temp_file_path).await;
-                                return Err(
-                                    format!(
-                                        "Failed to download chunk {}: HTTP {}",
-                                        chunk_id,
-                                        response.status()
-                                    ).into()
-                                );
-                            }
-                            tokio::time::sleep(
-                                Duration::from_millis(RETRY_DELAY_MS * (retries as u64))
-                            ).await;
-                        } else {
-                            // Client error, don't retry
-                            // Clean up temp file
-                            let _ = tokio::fs::remove_file(&temp_file_path).await;
-                            return Err(
-                                format!(
-                                    "Failed to download chunk {}: HTTP {}",
-                                    chunk_id,
-                                    response.status()
-                                ).into()
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        retries += 1;
-                        if retries >= MAX_RETRIES {
-                            let _ = tokio::fs::remove_file(&temp_file_path).await;
-                            return Err(
-                                format!(
-                                    "Network error downloading chunk {}: {}",
-                                    chunk_id,
-                                    e
-                                ).into()
-                            );
-                        }
-                        tokio::time::sleep(
-                            Duration::from_millis(RETRY_DELAY_MS * (retries as u64))
-                        ).await;
-                    }
-                }
+            if start != Some(written_bytes) || !same_total {
+                // Not the range that was asked for, or not the same file any more
+                written_bytes = 0;
+                total_bytes = None;
+                validator = None;
+                file.set_len(0).await.map_err(file_error)?;
+                file.seek(SeekFrom::Start(0)).await.map_err(file_error)?;
+                retries += 1;
+                continue;
             }
 
-            let chunk_data = chunk_data.ok_or("Failed to download chunk")?;
+            total_bytes = total.or(total_bytes);
+        } else if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            let (_, total) = content_range(&response).unwrap_or((None, None));
+            let already_complete =
+                written_bytes > 0 &&
+                total == Some(written_bytes) &&
+                total_bytes.is_none_or(|expected| expected == written_bytes);
+            if already_complete {
+                // The previous run had already received the whole file
+                total_bytes = total;
+                break;
+            }
 
-            let mut decoder = GzDecoder::new(&chunk_data[..]);
-            let mut decompressed_data = Vec::new();
-            decoder.read_to_end(&mut decompressed_data)?;
+            written_bytes = 0;
+            total_bytes = None;
+            validator = None;
+            file.set_len(0).await.map_err(file_error)?;
+            file.seek(SeekFrom::Start(0)).await.map_err(file_error)?;
+            retries += 1;
+            continue;
+        } else if status.is_success() {
+            // A fresh transfer, a server that ignores ranges or a file that changed since the
+            // partial download started. In every case the body is the whole file.
+            if written_bytes > 0 {
+                written_bytes = 0;
+                file.set_len(0).await.map_err(file_error)?;
+                file.seek(SeekFrom::Start(0)).await.map_err(file_error)?;
+            }
+            total_bytes = response.content_length();
+            validator = Some(RangeValidator::from_response(&response, total_bytes));
+        } else if status.is_server_error() {
+            retries += 1;
+            if retries >= max_retries {
+                return Err(LauncherError::Http { status: status.as_u16(), url: url.to_string() });
+            }
+            continue;
+        } else {
+            return Err(LauncherError::Http { status: status.as_u16(), url: url.to_string() });
+        }
 
-            output_file.write_all(&decompressed_data).await?;
+        if validator.is_none() {
+            validator = Some(RangeValidator::from_response(&response, total_bytes));
+        }
 
-            completed_size += decompressed_data.len() as i64;
-            let percentage = ((completed_size as f64) / (total_size as f64)) * 100.0;
+        let start_bytes = written_bytes;
+        let mut stream = response.bytes_stream();
+        let transfer = loop {
+            let next = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    file.flush().await.map_err(file_error)?;
+                    download_manager.keep_range_validator(build_id, validator).await;
+                    return Err(LauncherError::Cancelled);
+                }
+                next = timeout(Duration::from_secs(STALL_TIMEOUT_SECS), stream.next()) => next,
+            };
 
-            let speed = download_manager.update_speed_data(&build_id, completed_size as u64).await;
-            let remaining_bytes = total_size - completed_size;
-            let eta_seconds = if speed > 0.0 {
-                (remaining_bytes as f64) / speed
-            } else {
-                f64::INFINITY
+            let piece = match next {
+                Ok(Some(Ok(piece))) => piece,
+                Ok(Some(Err(e))) => {
+                    break Err(LauncherError::Network {
+                        message: format!("Error downloading file: {}", e),
+                        url: Some(url.to_string()),
+                    });
+                }
+                Ok(None) => {
+                    break Ok(());
+                }
+                Err(_) => {
+                    break Err(LauncherError::Network {
+                        message: format!(
+                            "Download stalled - no data received for {} seconds",
+                            STALL_TIMEOUT_SECS
+                        ),
+                        url: Some(url.to_string()),
+                    });
+                }
             };
-            let eta = format_time(eta_seconds);
+
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    file.flush().await.map_err(file_error)?;
+                    download_manager.keep_range_validator(build_id, validator).await;
+                    return Err(LauncherError::Cancelled);
+                }
+                _ = download_manager.bandwidth.acquire(build_id, piece.len() as u64) => {}
+            }
+            stats.add_bytes(piece.len() as u64);
+            file.write_all(&piece).await.map_err(file_error)?;
+            written_bytes += piece.len() as u64;
 
             if last_update.elapsed().as_millis() > (UPDATE_INTERVAL_MS as u128) {
-                let _ = window.emit("download:progress", DownloadProgress {
-                    build_id: build_id.clone(),
+                let speed = download_manager.update_speed_data(build_id, written_bytes).await;
+
+                let (percentage, eta) = match total_bytes {
+                    Some(total) if total > 0 => {
+                        let percentage = ((written_bytes as f64) / (total as f64)) * 100.0;
+                        let remaining_bytes = total.saturating_sub(written_bytes);
+                        let eta_seconds = if speed > 0.0 {
+                            (remaining_bytes as f64) / speed
+                        } else {
+                            f64::INFINITY
+                        };
+                        (percentage, format_time(eta_seconds))
+                    }
+                    _ => (0.0, "Unknown".to_string()),
+                };
+
+                download_manager.report_progress(window, DownloadProgress {
+                    build_id: build_id.to_string(),
                     percentage,
-                    downloaded_bytes: completed_size as u64,
-                    total_bytes: total_size as u64,
+                    downloaded_bytes: written_bytes,
+                    total_bytes: total_bytes.unwrap_or(0),
                     speed,
                     eta,
-                });
+                }).await;
 
                 last_update = std::time::Instant::now();
             }
-        }
+        };
+        file.flush().await.map_err(file_error)?;
 
-        output_file.flush().await?;
-        drop(output_file);
+        let error = match transfer {
+            Ok(()) if total_bytes.is_none_or(|total| written_bytes >= total) => {
+                break;
+            }
+            Ok(()) =>
+                LauncherError::Network {
+                    message: format!(
+                        "Connection closed after {} of {} bytes",
+                        written_bytes,
+                        total_bytes.unwrap_or(0)
+                    ),
+                    url: Some(url.to_string()),
+                },
+            Err(e) => e,
+        };
 
-        if file_path.exists() {
-            tokio::fs::remove_file(&file_path).await?;
+        // Only consecutive attempts that make no progress count against the retry limit
+        if written_bytes > start_bytes {
+            retries = 0;
+        }
+        retries += 1;
+        if retries >= max_retries {
+            return Err(error);
         }
+    }
+
+    Ok(total_bytes)
+}
 
-        tokio::fs::rename(&temp_file_path, &file_path).await?;
+/// Asks the server whether `url` can be fetched in byte ranges. When it can, the file is
+/// large enough to be worth splitting and `If-Range` can guard the ranges against the file
+/// changing underneath, returns a validator holding the segments to fetch.
+async fn probe_segments(client: &Client, url: &str) -> Option<RangeValidator> {
+    let response = client.head(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
     }
 
-    let _ = window.emit("download:progress", DownloadProgress {
-        build_id: build_id.clone(),
-        percentage: 100.0,
-        downloaded_bytes: total_size as u64,
-        total_bytes: total_size as u64,
-        speed: 0.0,
-        eta: "0s".to_string(),
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    let accepts_ranges = header(reqwest::header::ACCEPT_RANGES).is_some_and(|value| {
+        value.split(',').any(|unit| unit.trim().eq_ignore_ascii_case("bytes"))
     });
+    // The body of a HEAD response is empty, so the length has to come from the header itself
+    let total_bytes: u64 = header(reqwest::header::CONTENT_LENGTH)?.trim().parse().ok()?;
+    if !accepts_ranges || total_bytes < 2 * MIN_SEGMENT_BYTES {
+        return None;
+    }
 
-    let _ = window.emit("download:completed", build_id);
-
-    Ok(())
+    let mut validator = RangeValidator::from_response(&response, Some(total_bytes));
+    validator.if_range()?;
+    validator.segments = Some(Arc::new(Segment::split(total_bytes)));
+    Some(validator)
 }
 
-async fn download_file(
-    window: Window,
-    build_id: String,
-    url: &str,
-    destination: &str,
-    download_manager: &State<'_, DownloadManager>
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let client = Client::builder()
-        .pool_max_idle_per_host(20)
-        .pool_idle_timeout(std::time::Duration::from_secs(30))
-        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .build()?;
+/// Fetches the segments of `validator` over parallel connections into the destination of
+/// `transfer`, preallocated to the full size, and reports their combined progress. Each
+/// segment retries on its own. Returns false when the server turns out not to honour ranges
+/// after all.
+async fn download_segments(transfer: &FileTransfer<'_>, validator: RangeValidator) -> Result<bool, LauncherError> {
+    let FileTransfer { window, build_id, destination, cancel, download_manager, .. } = *transfer;
+    let (Some(segments), Some(total_bytes), Some(if_range)) = (
+        validator.segments.clone(),
+        validator.total_bytes,
+        validator.if_range().map(str::to_string),
+    ) else {
+        return Ok(false);
+    };
 
-    let mut response = None;
-    let mut retries = 0;
-    let mut last_error = String::new();
-
-    while retries < MAX_RETRIES && response.is_none() {
-        match client.get(url).send().await {
-            Ok(res) => {
-                if res.status().is_success() {
-                    response = Some(res);
-                } else if res.status().is_server_error() {
-                    last_error = format!("Server error: HTTP {}", res.status());
-                    retries += 1;
-                    tokio::time::sleep(
-                        Duration::from_millis(RETRY_DELAY_MS * (retries as u64))
-                    ).await;
-                } else {
-                    return Err(format!("Failed to download file: HTTP {}", res.status()).into());
+    let file_error = |e| LauncherError::io(destination, e);
+    let file = AsyncOpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(destination).await
+        .map_err(file_error)?;
+    file.set_len(total_bytes).await.map_err(file_error)?;
+    drop(file);
+
+    // Stops the other segments once one of them has failed for good
+    let stop = cancel.child_token();
+    let transfers = futures::future::join_all(
+        segments.iter().map(|segment| {
+            let (stop, if_range) = (&stop, &if_range);
+            async move {
+                let result = download_segment(transfer, segment, if_range, stop).await;
+                if result.is_err() {
+                    stop.cancel();
                 }
+                result
             }
-            Err(e) => {
-                last_error = format!("Network error: {}", e);
-                retries += 1;
-                if retries >= MAX_RETRIES {
-                    return Err(
-                        format!(
-                            "Failed to download after {} attempts. Last error: {}",
-                            MAX_RETRIES,
-                            last_error
-                        ).into()
-                    );
-                }
-                tokio::time::sleep(Duration::from_millis(RETRY_DELAY_MS * (retries as u64))).await;
+        })
+    );
+    tokio::pin!(transfers);
+
+    let mut ticker = tokio::time::interval(Duration::from_millis(UPDATE_INTERVAL_MS));
+    let results = loop {
+        tokio::select! {
+            results = &mut transfers => break results,
+            _ = ticker.tick() => {
+                let written_bytes: u64 = segments.iter().map(Segment::written).sum();
+                let speed = download_manager.update_speed_data(build_id, written_bytes).await;
+                let eta_seconds = if speed > 0.0 {
+                    (total_bytes.saturating_sub(written_bytes) as f64) / speed
+                } else {
+                    f64::INFINITY
+                };
+
+                download_manager.report_progress(window, DownloadProgress {
+                    build_id: build_id.to_string(),
+                    percentage: ((written_bytes as f64) / (total_bytes as f64)) * 100.0,
+                    downloaded_bytes: written_bytes,
+                    total_bytes,
+                    speed,
+                    eta: format_time(eta_seconds),
+                }).await;
+            }
+        }
+    };
+
+    if cancel.is_cancelled() {
+        // Kept for resume_download if this turns out to be a pause
+        download_manager.keep_range_validator(build_id, Some(validator)).await;
+        return Err(LauncherError::Cancelled);
+    }
+
+    let mut ranges_ignored = false;
+    for result in results {
+        match result {
+            Ok(()) => {}
+            Err(SegmentError::RangesIgnored) => {
+                ranges_ignored = true;
+            }
+            // Segments stopped because another one failed
+            Err(SegmentError::Failed(LauncherError::Cancelled)) => {}
+            Err(SegmentError::Failed(e)) => {
+                return Err(e);
             }
         }
     }
+    Ok(!ranges_ignored)
+}
+
+/// Downloads what is left of `segment`, retrying until an attempt makes no progress
+/// `max_retries` times in a row. `cancel` stops this segment together with the others.
+async fn download_segment(
+    transfer: &FileTransfer<'_>,
+    segment: &Segment,
+    if_range: &str,
+    cancel: &CancellationToken
+) -> Result<(), SegmentError> {
+    let FileTransfer { client, url, destination, stats, max_retries, .. } = *transfer;
+    let file_error = |e| LauncherError::io(destination, e);
+    let mut file = AsyncOpenOptions::new().write(true).open(destination).await.map_err(file_error)?;
+    let mut retries = 0;
 
-    let res = response.ok_or(format!("Failed to download after {} attempts", MAX_RETRIES))?;
+    loop {
+        let start = segment.start + segment.written();
+        if start >= segment.end {
+            return Ok(());
+        }
+        if retries > 0 {
+            stats.add_retries(1);
+            tokio::time::sleep(retry_delay(retries)).await;
+        }
 
-    let total_size = res.content_length().unwrap_or(0);
-    let has_content_length = total_size > 0;
+        let request = client
+            .get(url)
+            .header(reqwest::header::RANGE, format!("bytes={}-{}", start, segment.end - 1))
+            .header(reqwest::header::IF_RANGE, if_range);
+        let sent = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                return Err(LauncherError::Cancelled.into());
+            }
+            sent = request.send() => sent,
+        };
 
-    let mut file = tokio::fs::File::create(destination).await?;
+        let error = match sent {
+            Ok(response) if response.status() == reqwest::StatusCode::PARTIAL_CONTENT => {
+                if content_range(&response).and_then(|(first, _)| first) != Some(start) {
+                    return Err(SegmentError::RangesIgnored);
+                }
 
-    let mut stream = res.bytes_stream();
-    let mut downloaded_bytes = 0u64;
-    let start_time = std::time::Instant::now();
-    let mut last_update = std::time::Instant::now();
-    let mut last_download_time = std::time::Instant::now();
-    let timeout_duration = Duration::from_secs(30); // Timeout if no data received for 30 seconds
+                file.seek(SeekFrom::Start(start)).await.map_err(file_error)?;
+                let streamed = stream_segment(transfer, response, &mut file, segment, cancel).await;
+                file.flush().await.map_err(file_error)?;
+                match streamed {
+                    Ok(()) if segment.start + segment.written() >= segment.end => {
+                        return Ok(());
+                    }
+                    Ok(()) =>
+                        LauncherError::Network {
+                            message: format!(
+                                "Connection closed {} bytes short of the end of the segment at byte {}",
+                                segment.end - segment.start - segment.written(),
+                                segment.start
+                            ),
+                            url: Some(url.to_string()),
+                        },
+                    // Cancelled, or the file could not be written, which no retry is going to fix
+                    Err(
+                        e @ (
+                            | LauncherError::Cancelled
+                            | LauncherError::Io { .. }
+                            | LauncherError::DiskFull { .. }
+                        ),
+                    ) => {
+                        return Err(e.into());
+                    }
+                    Err(e) => e,
+                }
+            }
+            // A whole file instead of the range, either ranges are not supported after all or
+            // the file changed and If-Range no longer matches
+            Ok(response) if response.status().is_success() => {
+                return Err(SegmentError::RangesIgnored);
+            }
+            Ok(response) if response.status().is_server_error() =>
+                LauncherError::Http { status: response.status().as_u16(), url: url.to_string() },
+            Ok(response) => {
+                return Err(
+                    LauncherError::Http { status: response.status().as_u16(), url: url.to_string() }.into()
+                );
+            }
+            Err(e) =>
+                LauncherError::Network {
+                    message: format!("Error downloading file: {}", e),
+                    url: Some(url.to_string()),
+                },
+        };
 
-    while let Some(chunk_result) = stream.next().await {
-        {
-            let active_downloads = download_manager.active_downloads.lock().await;
-            if !active_downloads.contains(&build_id) {
-                file.flush().await?;
-                drop(file);
-                let _ = tokio::fs::remove_file(destination).await;
-                return Err("Download cancelled".into());
+        // Only consecutive attempts that make no progress count against the retry limit
+        if segment.start + segment.written() > start {
+            retries = 0;
+        }
+        retries += 1;
+        if retries >= max_retries {
+            return Err(error.into());
+        }
+    }
+}
+
+/// Writes the body of a range response into `file`, which is positioned at the first byte
+/// still missing from `segment`. Anything the server sends past the end of the segment is
+/// dropped so it cannot overwrite the next one.
+async fn stream_segment(
+    transfer: &FileTransfer<'_>,
+    response: reqwest::Response,
+    file: &mut AsyncFile,
+    segment: &Segment,
+    cancel: &CancellationToken
+) -> Result<(), LauncherError> {
+    let FileTransfer { build_id, destination, stats, download_manager, .. } = *transfer;
+    let url = response.url().to_string();
+    let file_error = |e| LauncherError::io(destination, e);
+    let mut stream = response.bytes_stream();
+
+    loop {
+        let next = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                return Err(LauncherError::Cancelled);
+            }
+            next = timeout(Duration::from_secs(STALL_TIMEOUT_SECS), stream.next()) => next,
+        };
+
+        let piece = match next {
+            Ok(Some(Ok(piece))) => piece,
+            Ok(Some(Err(e))) => {
+                return Err(LauncherError::Network {
+                    message: format!("Error downloading file: {}", e),
+                    url: Some(url),
+                });
+            }
+            Ok(None) => {
+                return Ok(());
+            }
+            Err(_) => {
+                return Err(LauncherError::Network {
+                    message: format!(
+                        "Download stalled - no data received for {} seconds",
+                        STALL_TIMEOUT_SECS
+                    ),
+                    url: Some(url),
+                });
+            }
+        };
+
+        let remaining = segment.end - segment.start - segment.written();
+        let piece = &piece[..piece.len().min(remaining as usize)];
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                return Err(LauncherError::Cancelled);
             }
+            _ = download_manager.bandwidth.acquire(build_id, piece.len() as u64) => {}
         }
+        stats.add_bytes(piece.len() as u64);
+        file.write_all(piece).await.map_err(file_error)?;
+        segment.written.fetch_add(piece.len() as u64, Ordering::Relaxed);
 
-        if last_download_time.elapsed() > timeout_duration {
-            file.flush().await?;
-            drop(file);
-            let _ = tokio::fs::remove_file(destination).await;
-            return Err("Download timed out - no data received for 30 seconds".into());
+        if segment.start + segment.written() >= segment.end {
+            return Ok(());
         }
+    }
+}
 
-        match chunk_result {
-            Ok(chunk) => {
-                file.write_all(&chunk).await?;
-                downloaded_bytes += chunk.len() as u64;
-                last_download_time = std::time::Instant::now();
+#[command]
+pub fn get_default_install_dir() -> Result<String, LauncherError> {
+    let home_dir = dirs::home_dir().ok_or_else(|| LauncherError::NotFound {
+        message: "Could not determine home directory".into(),
+    })?;
+    let default_dir = home_dir.join("Solaris").join("Builds");
 
-                if last_update.elapsed().as_millis() > (UPDATE_INTERVAL_MS as u128) {
-                    let speed = download_manager.update_speed_data(
-                        &build_id,
-                        downloaded_bytes
-                    ).await;
-
-                    let (percentage, eta) = if has_content_length && total_size > 0 {
-                        let percentage: f64 =
-                            ((downloaded_bytes as f64) / (total_size as f64)) * 100.0;
-                        let remaining_bytes = total_size.saturating_sub(downloaded_bytes);
-                        let eta_seconds = if speed > 0.0 {
-                            (remaining_bytes as f64) / speed
-                        } else {
-                            f64::INFINITY
-                        };
-                        (percentage, format_time(eta_seconds))
-                    } else {
-                        (0.0, "Unknown".to_string())
-                    };
+    if !default_dir.exists() {
+        std::fs::create_dir_all(&default_dir).map_err(|e| LauncherError::io(&default_dir, e))?;
+    }
 
-                    let _ = window.emit("download:progress", DownloadProgress {
-                        build_id: build_id.clone(),
-                        percentage,
-                        downloaded_bytes,
-                        total_bytes: total_size,
-                        speed,
-                        eta,
-                    });
+    Ok(default_dir.to_string_lossy().to_string())
+}
 
-                    last_update = std::time::Instant::now();
-                }
-            }
-            Err(e) => {
-                file.flush().await?;
-                drop(file);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                let _ = tokio::fs::remove_file(destination).await;
+    const DATA: &[u8] = b"decompressed chunk data";
 
-                return Err(format!("Error downloading file: {}", e).into());
-            }
+    fn chunk(compression: ChunkCompression, size: Option<u64>, hash: Option<&str>) -> ChunkRef {
+        ChunkRef {
+            id: 7,
+            hash: hash.map(str::to_string),
+            compression,
+            size,
+            offset: None,
         }
     }
 
-    file.flush().await?;
-    drop(file);
+    /// A `.part` file holding `contents`, in a folder that is removed with the returned guard.
+    fn part_file(contents: &[u8]) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("file.pak.part");
+        fs::write(&part_path, contents).unwrap();
+        (dir, part_path)
+    }
 
-    let file_size = tokio::fs::metadata(destination).await?.len();
-    if file_size == 0 {
-        let _ = tokio::fs::remove_file(destination).await;
-        return Err("Downloaded file is empty. The download may have failed.".into());
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
     }
 
-    if has_content_length && file_size != total_size {
-        let _ = tokio::fs::remove_file(destination).await;
-        return Err(
-            format!(
-                "Downloaded file size ({}) doesn't match expected size ({}). The download may be incomplete.",
-                file_size,
-                total_size
-            ).into()
+    fn queued(build_id: &str, priority: i32, required: bool) -> QueuedDownload {
+        QueuedDownload { build_id: build_id.to_string(), priority, required }
+    }
+
+    #[test]
+    fn builds_take_free_slots_in_queue_order() {
+        let queue = [queued("a", 5, false), queued("b", 0, false), queued("c", 0, false)];
+        assert!(takes_slot(&queue, 0, 2));
+        assert!(takes_slot(&queue, 1, 2));
+        assert!(!takes_slot(&queue, 2, 2));
+        assert!(!takes_slot(&queue, 0, 0));
+    }
+
+    #[test]
+    fn required_files_start_while_every_slot_is_taken() {
+        // max_concurrent builds are running, so no slot is free
+        let mut queue = vec![queued("build", 0, false)];
+        insert_by_priority(&mut queue, queued("pak", REQUIRED_FILE_PRIORITY, true));
+        assert_eq!(queue[0].build_id, "pak");
+
+        assert!(takes_slot(&queue, 0, 0));
+        assert!(!takes_slot(&queue, 1, 0));
+        // A required file waiting to be picked up does not hold a build back either
+        assert!(takes_slot(&queue, 1, 1));
+    }
+
+    #[test]
+    fn writes_every_compression_at_its_offset() {
+        let hash = format!("{:x}", Sha256::digest(DATA));
+        let bodies = [
+            (ChunkCompression::Gzip, gzip(DATA)),
+            (ChunkCompression::Zstd, zstd::encode_all(DATA, 0).unwrap()),
+            (ChunkCompression::None, DATA.to_vec()),
+        ];
+
+        for (compression, body) in bodies {
+            let (_dir, part_path) = part_file(b"0123456789");
+            let chunk = chunk(compression, Some(DATA.len() as u64), Some(&hash));
+            let size = write_chunk(body.as_slice(), &chunk, &part_path, 4, &CancellationToken::new()).unwrap();
+
+            assert_eq!(size, DATA.len() as u64);
+            assert_eq!(fs::read(&part_path).unwrap(), [b"0123".as_slice(), DATA].concat());
+        }
+    }
+
+    #[test]
+    fn creates_the_part_file_and_its_folder() {
+        let (dir, _) = part_file(b"");
+        let nested = dir.path().join("Engine").join("a.dll.part");
+
+        let chunk = chunk(ChunkCompression::None, None, None);
+        write_chunk(DATA, &chunk, &nested, 0, &CancellationToken::new()).unwrap();
+        assert_eq!(fs::read(&nested).unwrap(), DATA);
+    }
+
+    #[test]
+    fn rejects_chunks_that_do_not_match_the_manifest() {
+        let (_dir, part_path) = part_file(b"");
+        let cancel = CancellationToken::new();
+
+        let wrong_size = chunk(ChunkCompression::None, Some(DATA.len() as u64 + 1), None);
+        assert!(matches!(
+            write_chunk(DATA, &wrong_size, &part_path, 0, &cancel),
+            Err(LauncherError::Integrity { chunk_id: Some(7), .. })
+        ));
+
+        let wrong_hash = chunk(ChunkCompression::None, None, Some(&"0".repeat(64)));
+        assert!(matches!(
+            write_chunk(DATA, &wrong_hash, &part_path, 0, &cancel),
+            Err(LauncherError::Integrity { chunk_id: Some(7), .. })
+        ));
+
+        let corrupt = chunk(ChunkCompression::Gzip, None, None);
+        assert!(matches!(
+            write_chunk(DATA, &corrupt, &part_path, 0, &cancel),
+            Err(LauncherError::Integrity { chunk_id: Some(7), .. })
+        ));
+    }
+
+    #[test]
+    fn a_stopped_job_does_not_pass_a_cut_off_chunk() {
+        let (_dir, part_path) = part_file(b"");
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let chunk = chunk(ChunkCompression::None, None, None);
+        assert!(matches!(
+            write_chunk(&DATA[..4], &chunk, &part_path, 0, &cancel),
+            Err(LauncherError::Cancelled)
+        ));
+    }
+
+    fn ranges(segments: &[Segment]) -> Vec<(u64, u64)> {
+        segments
+            .iter()
+            .map(|segment| (segment.start, segment.end))
+            .collect()
+    }
+
+    #[test]
+    fn required_files_outlast_longer_outages() {
+        let outage = |retries: usize| (1..retries).map(retry_delay).sum::<Duration>();
+        assert!(outage(transfer_retries(false)) < Duration::from_secs(5));
+        assert!(outage(transfer_retries(true)) > Duration::from_secs(180));
+        assert_eq!(retry_delay(100), Duration::from_millis(MAX_RETRY_DELAY_MS));
+    }
+
+    #[test]
+    fn splits_files_into_adjacent_segments() {
+        let total_bytes = 4 * MIN_SEGMENT_BYTES;
+        let segments = Segment::split(total_bytes);
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[0].start, 0);
+        assert_eq!(segments[3].end, total_bytes);
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+            assert_eq!(pair[0].end - pair[0].start, MIN_SEGMENT_BYTES);
+        }
+
+        // One byte short of another segment still splits the same way
+        assert_eq!(Segment::split(5 * MIN_SEGMENT_BYTES - 1).len(), 4);
+        assert_eq!(Segment::split(100 * MIN_SEGMENT_BYTES).len(), SEGMENT_CONNECTIONS as usize);
+    }
+
+    #[test]
+    fn last_segment_takes_the_remainder() {
+        let total_bytes = 3 * MIN_SEGMENT_BYTES + 1;
+        let size = MIN_SEGMENT_BYTES + 1;
+        assert_eq!(
+            ranges(&Segment::split(total_bytes)),
+            [(0, size), (size, 2 * size), (2 * size, total_bytes)]
         );
     }
 
-    let _ = window.emit("download:completed", build_id);
+    #[test]
+    fn small_files_are_one_segment() {
+        assert_eq!(ranges(&Segment::split(5)), [(0, 5)]);
+        assert_eq!(ranges(&Segment::split(0)), [(0, 0)]);
+        assert_eq!(ranges(&Segment::split(2 * MIN_SEGMENT_BYTES - 1)), [(0, 2 * MIN_SEGMENT_BYTES - 1)]);
+    }
 
-    Ok(())
-}
+    #[test]
+    fn parses_content_ranges() {
+        assert_eq!(parse_content_range("bytes 0-99/100"), Some((Some(0), Some(100))));
+        assert_eq!(parse_content_range(" bytes 100-199/1000 "), Some((Some(100), Some(1000))));
+        assert_eq!(parse_content_range("bytes 0-99/*"), Some((Some(0), None)));
+        assert_eq!(parse_content_range("bytes */1000"), Some((None, Some(1000))));
+    }
 
-#[command]
-pub fn get_default_install_dir() -> Result<String, String> {
-    let home_dir = dirs
-        ::home_dir()
-        .ok_or_else(|| "Could not determine home directory".to_string())?;
-    let default_dir = home_dir.join("Solaris").join("Builds");
+    #[test]
+    fn rejects_malformed_content_ranges() {
+        assert_eq!(parse_content_range("bytes 0-99"), None);
+        assert_eq!(parse_content_range("items 0-99/100"), None);
+        assert_eq!(parse_content_range(""), None);
+        assert_eq!(parse_content_range("bytes x-99/y"), Some((None, None)));
+    }
 
-    if !default_dir.exists() {
-        std::fs::create_dir_all(&default_dir).map_err(|e| e.to_string())?;
+    /// Answers every request on a local port with `status` and `body` and returns its base URL.
+    async fn serve(status: &'static str, body: Vec<u8>) -> String {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let body = Arc::new(body);
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = socket.read(&mut request).await;
+                    let head = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&body).await;
+                });
+            }
+        });
+
+        base_url
     }
 
-    Ok(default_dir.to_string_lossy().to_string())
+    async fn health(mirrors: &MirrorSet, base_url: &str) -> i32 {
+        mirrors
+            .status().await
+            .into_iter()
+            .find(|status| status.base_url == base_url)
+            .unwrap().health
+    }
+
+    /// Extracts `archive` like a streamed download would, stopping after `entries` entries.
+    async fn extract_streamed(temp_target: &Path, archive: Vec<u8>, entries: usize) -> Result<(), LauncherError> {
+        let url = "https://example.test/13.40.tar.gz";
+        extract_marked(temp_target, url, async {
+            extract
+                ::extract_stream(archive.as_slice(), None, temp_target, ArchiveFormat::TarGz, |step| {
+                    step.processed_files < entries
+                })
+        }).await
+    }
+
+    fn tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        gzip(&builder.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn unfinished_extractions_stay_marked() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_target = dir.path().join("13.40.extracting");
+        let archive = tar_gz(&[("a.txt", b"a"), ("b.txt", b"b")]);
+
+        assert!(extract_streamed(&temp_target, archive.clone(), 1).await.is_err());
+        assert!(temp_target.join("a.txt").exists());
+        assert!(!temp_target.join("b.txt").exists());
+        assert!(temp_target.join(INCOMPLETE_MARKER).exists());
+
+        // A new run starts over and only unmarks the tree once everything is in place
+        extract_streamed(&temp_target, archive, usize::MAX).await.unwrap();
+        assert!(temp_target.join("b.txt").exists());
+        assert!(!temp_target.join(INCOMPLETE_MARKER).exists());
+    }
+
+    #[tokio::test]
+    async fn chunks_fail_over_to_the_next_mirror() {
+        let broken = serve("500 Internal Server Error", Vec::new()).await;
+        let working = serve("200 OK", gzip(DATA)).await;
+        let mirrors = Arc::new(MirrorSet::new([broken.clone(), working.clone()]));
+        let (_dir, part_path) = part_file(b"");
+
+        let fetcher = ChunkFetcher {
+            client: Client::builder().no_proxy().build().unwrap(),
+            origin: ChunkOrigin::Mirrors(mirrors.clone()),
+            build_id: "failover".to_string(),
+            bandwidth: Arc::new(BandwidthLimiter::new()),
+            stats: Arc::new(JobStats::new()),
+            cancel: CancellationToken::new(),
+        };
+        let target = ChunkTarget { part_path: part_path.clone(), start: chunk_starts_at(0) };
+        let chunk = chunk(ChunkCompression::Gzip, Some(DATA.len() as u64), None);
+
+        let written = fetcher.fetch("13.40/7.chunk", &chunk, &target).await.unwrap();
+        assert_eq!(written.size, DATA.len() as u64);
+        assert_eq!(fs::read(&part_path).unwrap(), DATA);
+
+        assert!(health(&mirrors, &broken).await < 0);
+        assert!(health(&mirrors, &working).await > 0);
+        assert_eq!(mirrors.ordered().await[0], working);
+    }
+
+    #[tokio::test]
+    async fn manifests_fail_over_to_the_next_mirror() {
+        const MANIFEST: &str =
+            r#"{ "Name": "13.40", "Size": 10, "Chunks": [{ "ChunksIds": [1], "File": "a.pak", "FileSize": 10 }] }"#;
+        let broken = serve("500 Internal Server Error", Vec::new()).await;
+        let working = serve("200 OK", MANIFEST.as_bytes().to_vec()).await;
+        let mirrors = MirrorSet::new([broken.clone(), working.clone()]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let manifest = fetch_cached::<ManifestFile>(
+            &Client::builder().no_proxy().build().unwrap(),
+            &mirrors,
+            &MetadataCache::with_dir(dir.path().to_path_buf()),
+            MirroredDocument {
+                what: "manifest",
+                path: "13.40/manifest.json",
+                cache_key: "manifest-13.40",
+                accept: Some(MANIFEST_ACCEPT),
+                attempts: 1,
+            }
+        ).await.unwrap();
+        assert!(!manifest.stale);
+        assert_eq!(manifest.data.name, "13.40");
+
+        assert!(health(&mirrors, &broken).await < 0);
+        assert!(health(&mirrors, &working).await > 0);
+    }
+
+    /// A manager running `request` as `build_id`, as if it had been given a slot.
+    async fn running(build_id: &str, request: JobRequest) -> DownloadManager {
+        let download_manager = DownloadManager::with_mirrors(["https://manifest.example.com"], [
+            "https://cdn.example.com",
+        ]);
+        download_manager.jobs.lock().await.insert(build_id.to_string(), DownloadJob::new(build_id, request));
+        download_manager.active_downloads.lock().await.push(build_id.to_string());
+        download_manager.cancellation_token(build_id).await;
+        download_manager
+    }
+
+    #[tokio::test]
+    async fn only_downloads_can_be_paused() {
+        let request = JobRequest::Repair { version: "13.40".into(), path: "/games/13.40".into() };
+        let download_manager = running("13.40", request).await;
+        assert!(matches!(download_manager.pause("13.40").await, Err(LauncherError::InvalidRequest { .. })));
+        assert!(!download_manager.cancellation_token("13.40").await.is_cancelled());
+        assert!(!download_manager.take_pause_request("13.40").await);
+
+        let request = JobRequest::Download(DownloadRequest {
+            build_id: "13.40".into(),
+            url: "https://example.com/13.40.zip".into(),
+            destination: "/games/13.40.zip".into(),
+            extract: false,
+            delete_after_extract: false,
+            use_manifest: None,
+            version: None,
+            max_workers: None,
+            priority: None,
+            bandwidth_limit: None,
+            stream_extract: None,
+            source: None,
+            required: false,
+        });
+        let download_manager = running("13.40", request).await;
+        assert!(download_manager.pause("13.40").await.unwrap());
+        assert!(download_manager.cancellation_token("13.40").await.is_cancelled());
+        assert!(!download_manager.pause("13.50").await.unwrap());
+    }
 }
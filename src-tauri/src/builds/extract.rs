@@ -0,0 +1,469 @@
+use flate2::read::GzDecoder;
+use std::fs::{ self, File };
+use std::io::{ self, BufWriter, Read, Write };
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use super::enclosed_path;
+use crate::error::LauncherError;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".7z") {
+            Some(Self::SevenZ)
+        } else {
+            None
+        }
+    }
+
+    /// Whether archives of this format can be extracted front to back while they download.
+    pub fn can_stream(&self) -> bool {
+        matches!(self, Self::Zip | Self::TarGz)
+    }
+
+    fn extension(&self) -> &'static [&'static str] {
+        match self {
+            Self::Zip => &[".zip"],
+            Self::TarGz => &[".tar.gz", ".tgz"],
+            Self::SevenZ => &[".7z"],
+        }
+    }
+}
+
+pub struct ExtractionStep<'a> {
+    pub current_file: &'a str,
+    pub processed_files: usize,
+    pub total_files: usize,
+    pub fraction: f64,
+}
+
+/// Returns the directory an archive is extracted into, which is the archive path without
+/// its archive extension (`Builds/13.40.zip` -> `Builds/13.40`).
+pub fn extraction_target(archive_path: &Path, format: ArchiveFormat) -> PathBuf {
+    let path = archive_path.to_string_lossy();
+    let lower = path.to_lowercase();
+
+    for extension in format.extension() {
+        if lower.ends_with(extension) {
+            return PathBuf::from(&path[..path.len() - extension.len()]);
+        }
+    }
+
+    archive_path.with_extension("")
+}
+
+/// Extracts `archive_path` into `target`. `on_step` is called after every entry and returns
+/// `false` to stop extraction, in which case `LauncherError::Cancelled` is returned.
+pub fn extract_archive(
+    archive_path: &Path,
+    target: &Path,
+    format: ArchiveFormat,
+    mut on_step: impl FnMut(ExtractionStep) -> bool
+) -> Result<(), LauncherError> {
+    fs::create_dir_all(target).map_err(|e| LauncherError::io(target, e))?;
+
+    match format {
+        ArchiveFormat::Zip => extract_zip(archive_path, target, &mut on_step),
+        ArchiveFormat::TarGz => extract_tar_gz(archive_path, target, &mut on_step),
+        ArchiveFormat::SevenZ => extract_7z(archive_path, target, &mut on_step),
+    }
+}
+
+/// Extracts an archive read front to back from `reader` into `target`, for archives that are
+/// extracted as they download and never stored. `archive_size` is only used for progress.
+///
+/// Zip archives keep their index at the end, so entries are read from their local headers
+/// instead. That only works when every entry records its sizes there, which rules out zip
+/// files written as a stream with data descriptors.
+pub fn extract_stream(
+    reader: impl Read,
+    archive_size: Option<u64>,
+    target: &Path,
+    format: ArchiveFormat,
+    mut on_step: impl FnMut(ExtractionStep) -> bool
+) -> Result<(), LauncherError> {
+    fs::create_dir_all(target).map_err(|e| LauncherError::io(target, e))?;
+
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let reader = CountingReader { inner: reader, count: bytes_read.clone() };
+    let archive_size = archive_size.unwrap_or(0);
+
+    match format {
+        ArchiveFormat::Zip => unpack_zip_stream(reader, &bytes_read, archive_size, target, &mut on_step),
+        ArchiveFormat::TarGz => unpack_tar_gz(reader, &bytes_read, archive_size, target, &mut on_step),
+        ArchiveFormat::SevenZ =>
+            Err(LauncherError::InvalidRequest {
+                message: "7z archives cannot be extracted while they download".into(),
+            }),
+    }
+}
+
+/// An archive at `path` that could not be read.
+fn extraction_error(message: String, path: &Path) -> LauncherError {
+    LauncherError::Extraction { message, path: Some(path.to_string_lossy().to_string()) }
+}
+
+fn unsafe_path_error(name: &str, target: &Path) -> LauncherError {
+    extraction_error(format!("Archive entry has an unsafe path: {}", name), target)
+}
+
+fn extract_zip(
+    archive_path: &Path,
+    target: &Path,
+    on_step: &mut impl FnMut(ExtractionStep) -> bool
+) -> Result<(), LauncherError> {
+    let file = File::open(archive_path).map_err(|e| LauncherError::io(archive_path, e))?;
+    let mut archive = zip::ZipArchive
+        ::new(file)
+        .map_err(|e| extraction_error(format!("Failed to read zip archive: {}", e), archive_path))?;
+    let total_files = archive.len();
+
+    for index in 0..total_files {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| extraction_error(format!("Failed to read zip entry: {}", e), archive_path))?;
+
+        let Some(relative_path) = entry.enclosed_name() else {
+            return Err(unsafe_path_error(entry.name(), target));
+        };
+        let out_path = target.join(&relative_path);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| LauncherError::io(&out_path, e))?;
+        } else {
+            write_entry(&mut entry, &out_path)?;
+        }
+
+        let step = ExtractionStep {
+            current_file: &relative_path.to_string_lossy(),
+            processed_files: index + 1,
+            total_files,
+            fraction: ((index + 1) as f64) / (total_files as f64),
+        };
+        if !on_step(step) {
+            return Err(LauncherError::Cancelled);
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_tar_gz(
+    archive_path: &Path,
+    target: &Path,
+    on_step: &mut impl FnMut(ExtractionStep) -> bool
+) -> Result<(), LauncherError> {
+    let file = File::open(archive_path).map_err(|e| LauncherError::io(archive_path, e))?;
+    let archive_size = file
+        .metadata()
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+
+    // tar.gz has no index, so progress is measured by how much of the compressed file was read
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let reader = CountingReader { inner: file, count: bytes_read.clone() };
+    unpack_tar_gz(reader, &bytes_read, archive_size, target, on_step)
+}
+
+fn unpack_tar_gz(
+    reader: impl Read,
+    bytes_read: &AtomicU64,
+    archive_size: u64,
+    target: &Path,
+    on_step: &mut impl FnMut(ExtractionStep) -> bool
+) -> Result<(), LauncherError> {
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+
+    let entries = archive
+        .entries()
+        .map_err(|e| extraction_error(format!("Failed to read tar archive: {}", e), target))?;
+
+    for (index, entry) in entries.enumerate() {
+        let mut entry = entry.map_err(|e| extraction_error(format!("Failed to read tar entry: {}", e), target))?;
+        let current_file = entry
+            .path()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        // Failing to write is told apart from a damaged archive, a full disk included
+        if !entry.unpack_in(target).map_err(|e| LauncherError::io(target.join(&current_file), e))? {
+            return Err(unsafe_path_error(&current_file, target));
+        }
+
+        let step = ExtractionStep {
+            current_file: &current_file,
+            processed_files: index + 1,
+            total_files: index + 1,
+            fraction: read_fraction(bytes_read, archive_size),
+        };
+        if !on_step(step) {
+            return Err(LauncherError::Cancelled);
+        }
+    }
+
+    Ok(())
+}
+
+fn unpack_zip_stream(
+    mut reader: impl Read,
+    bytes_read: &AtomicU64,
+    archive_size: u64,
+    target: &Path,
+    on_step: &mut impl FnMut(ExtractionStep) -> bool
+) -> Result<(), LauncherError> {
+    let mut processed_files = 0;
+
+    loop {
+        let mut entry = match zip::read::read_zipfile_from_stream(&mut reader) {
+            Ok(Some(entry)) => entry,
+            // Reached the central directory, every entry has been read
+            Ok(None) => {
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(extraction_error(format!("Failed to read zip entry: {}", e), target));
+            }
+        };
+
+        let Some(relative_path) = entry.enclosed_name() else {
+            return Err(unsafe_path_error(entry.name(), target));
+        };
+        let out_path = target.join(&relative_path);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| LauncherError::io(&out_path, e))?;
+        } else {
+            write_entry(&mut entry, &out_path)?;
+        }
+
+        processed_files += 1;
+        let step = ExtractionStep {
+            current_file: &relative_path.to_string_lossy(),
+            processed_files,
+            total_files: processed_files,
+            fraction: read_fraction(bytes_read, archive_size),
+        };
+        if !on_step(step) {
+            return Err(LauncherError::Cancelled);
+        }
+    }
+}
+
+/// How much of an archive of `archive_size` bytes has been read, or zero when the size is unknown.
+fn read_fraction(bytes_read: &AtomicU64, archive_size: u64) -> f64 {
+    if archive_size > 0 {
+        ((bytes_read.load(Ordering::Relaxed) as f64) / (archive_size as f64)).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+fn extract_7z(
+    archive_path: &Path,
+    target: &Path,
+    on_step: &mut impl FnMut(ExtractionStep) -> bool
+) -> Result<(), LauncherError> {
+    let mut archive = sevenz_rust::SevenZReader
+        ::open(archive_path, sevenz_rust::Password::empty())
+        .map_err(|e| extraction_error(format!("Failed to read 7z archive: {}", e), archive_path))?;
+    let total_files = archive.archive().files.len();
+    let mut processed_files = 0;
+    // Why the callback stopped the reader, which only carries its own error type
+    let mut stopped = None;
+
+    let result = archive.for_each_entries(|entry, reader| {
+        let Some(relative_path) = enclosed_path(entry.name()) else {
+            stopped = Some(unsafe_path_error(entry.name(), target));
+            return Err(sevenz_rust::Error::other("Unsafe path"));
+        };
+        let out_path = target.join(&relative_path);
+
+        let written = if entry.is_directory() {
+            fs::create_dir_all(&out_path).map_err(|e| LauncherError::io(&out_path, e))
+        } else {
+            write_entry(reader, &out_path)
+        };
+        if let Err(e) = written {
+            stopped = Some(e);
+            return Err(sevenz_rust::Error::other("Write failed"));
+        }
+
+        processed_files += 1;
+        let step = ExtractionStep {
+            current_file: entry.name(),
+            processed_files,
+            total_files,
+            fraction: (processed_files as f64) / (total_files.max(1) as f64),
+        };
+        if !on_step(step) {
+            stopped = Some(LauncherError::Cancelled);
+            return Err(sevenz_rust::Error::other("Extraction cancelled"));
+        }
+
+        Ok(true)
+    });
+
+    match (result, stopped) {
+        (Ok(()), _) => Ok(()),
+        (Err(_), Some(error)) => Err(error),
+        (Err(e), None) => Err(extraction_error(format!("Failed to extract 7z archive: {}", e), archive_path)),
+    }
+}
+
+fn write_entry(reader: &mut dyn Read, out_path: &Path) -> Result<(), LauncherError> {
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| LauncherError::io(parent, e))?;
+    }
+
+    let file = File::create(out_path).map_err(|e| LauncherError::io(out_path, e))?;
+    let mut writer = BufWriter::new(file);
+    // Flushed here, a BufWriter dropped with data left in it silently loses the error
+    io::copy(reader, &mut writer)
+        .and_then(|_| writer.flush())
+        .map_err(|e| LauncherError::io(out_path, e))?;
+
+    Ok(())
+}
+
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const ENTRIES: &[(&str, &[u8])] = &[
+        ("Engine/Binaries/game.exe", b"binary"),
+        ("Game/Content/a.pak", b"pak data"),
+        ("readme.txt", b""),
+    ];
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Names are written into the headers as they are, because `tar::Builder` refuses `..`.
+    fn tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, data) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn seven_z(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = sevenz_rust::SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for (name, data) in entries {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry.has_stream = true;
+            writer.push_archive_entry(entry, Some(*data)).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Writes `archive` as `13.40` with the extension of `format` into a fresh folder and
+    /// extracts it into `13.40`.
+    fn extract(archive: &[u8], format: ArchiveFormat) -> (Result<(), LauncherError>, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join(format!("13.40{}", format.extension()[0]));
+        fs::write(&archive_path, archive).unwrap();
+
+        let target = extraction_target(&archive_path, format);
+        (extract_archive(&archive_path, &target, format, |_| true), dir)
+    }
+
+    fn assert_extracted(target: &Path) {
+        for (name, data) in ENTRIES {
+            assert_eq!(fs::read(target.join(name)).unwrap(), *data, "{}", name);
+        }
+    }
+
+    #[test]
+    fn extracts_every_format() {
+        for (archive, format) in [
+            (zip(ENTRIES), ArchiveFormat::Zip),
+            (tar_gz(ENTRIES), ArchiveFormat::TarGz),
+            (seven_z(ENTRIES), ArchiveFormat::SevenZ),
+        ] {
+            let (result, dir) = extract(&archive, format);
+            result.unwrap();
+            assert_extracted(&dir.path().join("13.40"));
+        }
+    }
+
+    #[test]
+    fn extracts_streams() {
+        for (archive, format) in [(zip(ENTRIES), ArchiveFormat::Zip), (tar_gz(ENTRIES), ArchiveFormat::TarGz)] {
+            let dir = tempfile::tempdir().unwrap();
+            let size = Some(archive.len() as u64);
+            extract_stream(archive.as_slice(), size, dir.path(), format, |_| true).unwrap();
+            assert_extracted(dir.path());
+        }
+    }
+
+    #[test]
+    fn rejects_entries_outside_the_target() {
+        let entries: &[(&str, &[u8])] = &[("a.txt", b"a"), ("../escaped.txt", b"escaped")];
+
+        for (archive, format) in [
+            (zip(entries), ArchiveFormat::Zip),
+            (tar_gz(entries), ArchiveFormat::TarGz),
+            (seven_z(entries), ArchiveFormat::SevenZ),
+        ] {
+            let (result, dir) = extract(&archive, format);
+            let error = result.unwrap_err();
+            assert!(error.to_string().contains("unsafe path"), "{:?}: {}", format, error);
+            assert!(!dir.path().join("escaped.txt").exists(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = tar_gz(ENTRIES);
+
+        let result = extract_stream(archive.as_slice(), None, dir.path(), ArchiveFormat::TarGz, |step| {
+            step.processed_files < 1
+        });
+        assert!(matches!(result, Err(LauncherError::Cancelled)));
+        assert!(dir.path().join(ENTRIES[0].0).exists());
+        assert!(!dir.path().join(ENTRIES[1].0).exists());
+    }
+}
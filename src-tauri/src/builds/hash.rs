@@ -0,0 +1,30 @@
+use sha2::{ Digest, Sha256 };
+use std::fs::File;
+use std::io::{ self, Read };
+use std::path::{ Path, PathBuf };
+
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Returns the lowercase hex SHA-256 of the file at `path`. Hashing a multi gigabyte file
+/// keeps a core busy for seconds, so it runs on a blocking thread instead of the runtime.
+pub async fn hash_file(path: &Path) -> io::Result<String> {
+    let path: PathBuf = path.to_path_buf();
+
+    tokio::task
+        ::spawn_blocking(move || {
+            let mut file = File::open(&path)?;
+            let mut hasher = Sha256::new();
+            let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+
+            Ok(format!("{:x}", hasher.finalize()))
+        }).await
+        .map_err(io::Error::other)?
+}
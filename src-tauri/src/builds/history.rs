@@ -0,0 +1,226 @@
+use serde::{ Deserialize, Serialize };
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::time::{ Instant, SystemTime, UNIX_EPOCH };
+use tokio::sync::Mutex;
+
+use super::atomic_write_async;
+use crate::error::LauncherError;
+
+const MAX_HISTORY_ENTRIES: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Download,
+    Update,
+    Repair,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobOutcome {
+    Completed,
+    Failed,
+    Cancelled,
+    Paused,
+}
+
+impl JobOutcome {
+    pub fn of_error(error: &LauncherError) -> Self {
+        match error {
+            LauncherError::Cancelled => Self::Cancelled,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// What a job was, as opposed to how it went.
+pub struct JobInfo {
+    pub build_id: String,
+    pub kind: JobKind,
+    pub version: Option<String>,
+    /// The URL of a plain download, or the mirror a manifest job started on.
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub build_id: String,
+    pub kind: JobKind,
+    pub version: Option<String>,
+    pub source: String,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub duration_ms: u64,
+    /// Bytes received from the network, chunks served from the cache not included.
+    pub downloaded_bytes: u64,
+    /// Bytes per second over the whole run.
+    pub average_speed: f64,
+    pub peak_speed: f64,
+    pub retries: u64,
+    /// Chunks that failed at least one attempt, whether or not a later attempt succeeded.
+    pub failed_chunks: Vec<i32>,
+    pub outcome: JobOutcome,
+    pub error_code: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Numbers collected while a job runs and turned into a `HistoryEntry` once it ends. Chunk
+/// tasks update them concurrently, hence the atomics.
+pub struct JobStats {
+    started_at: SystemTime,
+    started: Instant,
+    downloaded_bytes: AtomicU64,
+    /// Bytes copied from the install or the chunk cache instead of being downloaded.
+    reused_bytes: AtomicU64,
+    peak_speed: AtomicU64,
+    retries: AtomicU64,
+    failed_chunks: std::sync::Mutex<BTreeSet<i32>>,
+}
+
+impl Default for JobStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobStats {
+    pub fn new() -> Self {
+        Self {
+            started_at: SystemTime::now(),
+            started: Instant::now(),
+            downloaded_bytes: AtomicU64::new(0),
+            reused_bytes: AtomicU64::new(0),
+            peak_speed: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            failed_chunks: std::sync::Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.downloaded_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_reused_bytes(&self, bytes: u64) {
+        self.reused_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.downloaded_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn reused_bytes(&self) -> u64 {
+        self.reused_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn add_retries(&self, retries: u64) {
+        self.retries.fetch_add(retries, Ordering::Relaxed);
+    }
+
+    pub fn observe_speed(&self, bytes_per_second: f64) {
+        self.peak_speed.fetch_max(bytes_per_second as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_failed_chunk(&self, chunk_id: i32) {
+        if let Ok(mut failed_chunks) = self.failed_chunks.lock() {
+            failed_chunks.insert(chunk_id);
+        }
+    }
+
+    pub fn finish(&self, job: JobInfo, outcome: JobOutcome, error: Option<&LauncherError>) -> HistoryEntry {
+        let started_at = unix_millis(self.started_at);
+        let duration = self.started.elapsed();
+        let downloaded_bytes = self.downloaded_bytes();
+
+        HistoryEntry {
+            id: format!("{}-{}", job.build_id, started_at),
+            build_id: job.build_id,
+            kind: job.kind,
+            version: job.version,
+            source: job.source,
+            started_at,
+            finished_at: unix_millis(SystemTime::now()),
+            duration_ms: duration.as_millis() as u64,
+            downloaded_bytes,
+            average_speed: if duration.as_secs_f64() > 0.0 {
+                (downloaded_bytes as f64) / duration.as_secs_f64()
+            } else {
+                0.0
+            },
+            peak_speed: self.peak_speed.load(Ordering::Relaxed) as f64,
+            retries: self.retries.load(Ordering::Relaxed),
+            failed_chunks: self.failed_chunks
+                .lock()
+                .map(|failed_chunks| failed_chunks.iter().copied().collect())
+                .unwrap_or_default(),
+            outcome,
+            error_code: error.map(|error| error.code().to_string()),
+            error: error.map(|error| error.to_string()),
+        }
+    }
+}
+
+/// Every finished download, update and repair, oldest first, kept on disk across sessions.
+pub struct DownloadHistory {
+    path: Option<PathBuf>,
+    entries: Mutex<Vec<HistoryEntry>>,
+}
+
+impl Default for DownloadHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownloadHistory {
+    pub fn new() -> Self {
+        let path = dirs::data_local_dir().map(|dir| dir.join("com.solarisfn.org").join("history.json"));
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    pub async fn entries(&self) -> Vec<HistoryEntry> {
+        self.entries.lock().await.clone()
+    }
+
+    pub async fn record(&self, entry: HistoryEntry) -> io::Result<()> {
+        // Held until the file is written so two jobs finishing together save in order
+        let mut entries = self.entries.lock().await;
+        entries.push(entry);
+        let overflow = entries.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        entries.drain(..overflow);
+        let data = serde_json::to_vec(&*entries)?;
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        atomic_write_async(path, &data).await
+    }
+}
+
+/// Writes `entries` to `path` as indented JSON, ready to attach to a bug report.
+pub async fn export(entries: &[HistoryEntry], path: &Path) -> Result<(), LauncherError> {
+    let data = serde_json::to_vec_pretty(entries)?;
+    tokio::fs::write(path, data).await.map_err(|e| LauncherError::io(path, e))
+}
+
+pub fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis() as u64)
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+use std::time::SystemTime;
+
+use super::download_manager::{ DownloadProgress, DownloadRequest, ExtractionProgress };
+use super::history::unix_millis;
+use crate::error::LauncherError;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    ResolvingManifest,
+    Downloading,
+    Verifying,
+    Extracting,
+    Finalizing,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    /// Whether the job has stopped, for good or until it is resumed.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Paused | Self::Completed | Self::Failed | Self::Cancelled)
+    }
+
+    pub fn of_error(error: &LauncherError) -> Self {
+        match error {
+            LauncherError::Cancelled => Self::Cancelled,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// The parameters a job was started with.
+#[derive(Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum JobRequest {
+    Download(DownloadRequest),
+    Update {
+        from_version: String,
+        to_version: String,
+        path: String,
+    },
+    Repair {
+        version: String,
+        path: String,
+    },
+}
+
+/// A download, update or repair as the UI sees it: what was asked for, where it stands and
+/// the last progress it reported.
+#[derive(Serialize, Clone)]
+pub struct DownloadJob {
+    pub build_id: String,
+    pub state: JobState,
+    pub request: JobRequest,
+    pub progress: Option<DownloadProgress>,
+    pub extraction: Option<ExtractionProgress>,
+    pub error: Option<LauncherError>,
+    /// Milliseconds since the Unix epoch.
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl DownloadJob {
+    pub fn new(build_id: &str, request: JobRequest) -> Self {
+        let now = unix_millis(SystemTime::now());
+        Self {
+            build_id: build_id.to_string(),
+            state: JobState::Queued,
+            request,
+            progress: None,
+            extraction: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn set_state(&mut self, state: JobState) {
+        self.state = state;
+        self.updated_at = unix_millis(SystemTime::now());
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{ Deserialize, Serialize };
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::{ atomic_write, file_name_for };
+
+const JOURNAL_EXTENSION: &str = "journal";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalFile {
+    pub file: String,
+    pub chunks: Vec<i32>,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadJournal {
+    pub build_id: String,
+    pub version: String,
+    pub url: String,
+    pub destination: String,
+    pub install_path: String,
+    pub max_workers: Option<usize>,
+    /// The `source` of the request, so a resumed install reads from the same place.
+    #[serde(default)]
+    pub source: Option<String>,
+    pub completed_files: HashSet<String>,
+    pub current_file: Option<JournalFile>,
+    pub completed_bytes: u64,
+    #[serde(skip)]
+    ephemeral: bool,
+}
+
+impl DownloadJournal {
+    pub fn new(
+        build_id: &str,
+        version: &str,
+        url: &str,
+        destination: &str,
+        install_path: &str,
+        max_workers: Option<usize>,
+        source: Option<String>
+    ) -> Self {
+        Self {
+            build_id: build_id.to_string(),
+            version: version.to_string(),
+            url: url.to_string(),
+            destination: destination.to_string(),
+            install_path: install_path.to_string(),
+            max_workers,
+            source,
+            completed_files: HashSet::new(),
+            current_file: None,
+            completed_bytes: 0,
+            ephemeral: false,
+        }
+    }
+
+    /// A journal that tracks progress in memory only, for jobs such as updates that
+    /// cannot be resumed through `resume_download`.
+    pub fn ephemeral(build_id: &str, install_path: &str) -> Self {
+        Self {
+            ephemeral: true,
+            ..Self::new(build_id, "", "", install_path, install_path, None, None)
+        }
+    }
+
+    pub fn load(build_id: &str) -> Option<Self> {
+        let data = fs::read(journal_path(build_id)?).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    pub fn load_all() -> Vec<Self> {
+        let Some(dir) = journal_dir() else {
+            return Vec::new();
+        };
+
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == JOURNAL_EXTENSION))
+            .filter_map(|path| fs::read(path).ok())
+            .filter_map(|data| serde_json::from_slice::<Self>(&data).ok())
+            .collect()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if self.ephemeral {
+            return Ok(());
+        }
+
+        let path = journal_path(&self.build_id).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "Could not determine journal directory")
+        })?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        atomic_write(&path, &serde_json::to_vec(self)?)
+    }
+
+    pub fn remove(build_id: &str) {
+        if let Some(path) = journal_path(build_id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    pub fn is_file_completed(&self, file: &str) -> bool {
+        self.completed_files.contains(file)
+    }
+
+    pub fn record_chunk(&mut self, file: &str, chunk_id: i32, bytes: u64) {
+        match &mut self.current_file {
+            Some(current) if current.file == file => {
+                current.chunks.push(chunk_id);
+                current.bytes += bytes;
+            }
+            _ => {
+                self.current_file = Some(JournalFile {
+                    file: file.to_string(),
+                    chunks: vec![chunk_id],
+                    bytes,
+                });
+            }
+        }
+
+        self.completed_bytes += bytes;
+    }
+
+    pub fn complete_file(&mut self, file: &str) {
+        if self.current_file.as_ref().is_some_and(|current| current.file == file) {
+            self.current_file = None;
+        }
+
+        self.completed_files.insert(file.to_string());
+    }
+
+    pub fn discard_current_file(&mut self) {
+        if let Some(current) = self.current_file.take() {
+            self.completed_bytes = self.completed_bytes.saturating_sub(current.bytes);
+        }
+    }
+}
+
+fn journal_dir() -> Option<PathBuf> {
+    Some(dirs::data_local_dir()?.join("com.solarisfn.org").join("downloads"))
+}
+
+fn journal_path(build_id: &str) -> Option<PathBuf> {
+    Some(journal_dir()?.join(format!("{}.{}", file_name_for(build_id), JOURNAL_EXTENSION)))
+}
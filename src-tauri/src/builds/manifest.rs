@@ -0,0 +1,315 @@
+use serde::{ Deserialize, Deserializer, Serialize };
+
+use super::enclosed_path;
+
+/// Newest manifest format this launcher can read. Manifests without a `ManifestVersion`
+/// field are version 1.
+pub const MANIFEST_VERSION: u32 = 2;
+/// Sent with manifest requests so a mirror that can serve several formats picks one we read.
+pub const MANIFEST_ACCEPT: &str =
+    "application/vnd.solaris.manifest+json; version=2, application/json; q=0.5";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkCompression {
+    /// The only format version 1 manifests know.
+    #[default]
+    Gzip,
+    Zstd,
+    None,
+}
+
+/// Everything needed to fetch and check one chunk of a file.
+#[derive(Clone)]
+pub struct ChunkRef {
+    pub id: i32,
+    pub hash: Option<String>,
+    pub compression: ChunkCompression,
+    /// Decompressed size, when the manifest gives one.
+    pub size: Option<u64>,
+    /// Position in the assembled file, when the manifest gives chunk offsets or sizes.
+    pub offset: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChunkedFile {
+    #[serde(rename = "ChunksIds")]
+    pub chunks_ids: Vec<i32>,
+    #[serde(rename = "File")]
+    pub file: String,
+    #[serde(rename = "FileSize")]
+    pub file_size: i64,
+    #[serde(rename = "ChunksHashes", default, skip_serializing_if = "Option::is_none")]
+    pub chunks_hashes: Option<Vec<String>>,
+    #[serde(rename = "FileHash", default, skip_serializing_if = "Option::is_none")]
+    pub file_hash: Option<String>,
+    /// Per chunk compression, overriding the manifest wide `Compression`.
+    #[serde(rename = "ChunksCompression", default, skip_serializing_if = "Option::is_none")]
+    pub chunks_compression: Option<Vec<ChunkCompression>>,
+    /// Decompressed size of every chunk.
+    #[serde(rename = "ChunksSizes", default, skip_serializing_if = "Option::is_none")]
+    pub chunks_sizes: Option<Vec<u64>>,
+    /// Position of every chunk in the assembled file.
+    #[serde(rename = "ChunksOffsets", default, skip_serializing_if = "Option::is_none")]
+    pub chunks_offsets: Option<Vec<u64>>,
+}
+
+impl ChunkedFile {
+    pub fn chunk_hash(&self, index: usize) -> Option<String> {
+        self.chunks_hashes.as_ref().and_then(|hashes| hashes.get(index).cloned())
+    }
+
+    pub fn chunk(&self, index: usize, default_compression: ChunkCompression) -> ChunkRef {
+        ChunkRef {
+            id: self.chunks_ids[index],
+            hash: self.chunk_hash(index),
+            compression: self.chunks_compression
+                .as_ref()
+                .and_then(|compression| compression.get(index).copied())
+                .unwrap_or(default_compression),
+            size: self.chunks_sizes.as_ref().and_then(|sizes| sizes.get(index).copied()),
+            offset: self.chunk_offset(index),
+        }
+    }
+
+    /// Where chunk `index` starts in the assembled file. Manifests that list chunk sizes but
+    /// no offsets have their chunks back to back.
+    pub fn chunk_offset(&self, index: usize) -> Option<u64> {
+        match (&self.chunks_offsets, &self.chunks_sizes) {
+            (Some(offsets), _) => offsets.get(index).copied(),
+            (None, Some(sizes)) => Some(sizes.iter().take(index).sum()),
+            (None, None) => None,
+        }
+    }
+
+    /// Checks that the file stays inside the install folder, that the per chunk lists line up
+    /// with the chunk IDs and that chunk sizes and offsets cover the file without gaps or overlaps.
+    fn validate(&self) -> Result<(), String> {
+        if enclosed_path(&self.file).is_none() {
+            return Err(format!("{} is not a path inside the install folder", self.file));
+        }
+
+        let chunk_count = self.chunks_ids.len();
+        let list_lengths = [
+            ("ChunksHashes", self.chunks_hashes.as_ref().map(Vec::len)),
+            ("ChunksCompression", self.chunks_compression.as_ref().map(Vec::len)),
+            ("ChunksSizes", self.chunks_sizes.as_ref().map(Vec::len)),
+            ("ChunksOffsets", self.chunks_offsets.as_ref().map(Vec::len)),
+        ];
+        for (name, length) in list_lengths {
+            if let Some(length) = length.filter(|length| *length != chunk_count) {
+                return Err(
+                    format!("{} of {} has {} entries for {} chunks", name, self.file, length, chunk_count)
+                );
+            }
+        }
+
+        if self.chunks_offsets.is_some() && self.chunks_sizes.is_none() {
+            return Err(format!("{} has chunk offsets but no chunk sizes", self.file));
+        }
+
+        if let Some(sizes) = &self.chunks_sizes {
+            let mut expected_offset = 0u64;
+            for (index, size) in sizes.iter().enumerate() {
+                let offset = self.chunks_offsets.as_ref().map_or(expected_offset, |offsets| offsets[index]);
+                if offset != expected_offset {
+                    return Err(
+                        format!(
+                            "Chunk {} of {} starts at byte {}, expected {}",
+                            self.chunks_ids[index],
+                            self.file,
+                            offset,
+                            expected_offset
+                        )
+                    );
+                }
+                expected_offset += size;
+            }
+
+            if expected_offset != (self.file_size.max(0) as u64) {
+                return Err(
+                    format!(
+                        "Chunks of {} add up to {} bytes but the file is {} bytes",
+                        self.file,
+                        expected_offset,
+                        self.file_size
+                    )
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ManifestFile {
+    #[serde(
+        rename = "ManifestVersion",
+        default = "legacy_manifest_version",
+        deserialize_with = "supported_manifest_version"
+    )]
+    pub manifest_version: u32,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Chunks")]
+    pub chunks: Vec<ChunkedFile>,
+    #[serde(rename = "Size")]
+    pub size: i64,
+    /// Compression of every chunk without an entry in `ChunksCompression`.
+    #[serde(rename = "Compression", default)]
+    pub compression: ChunkCompression,
+    #[serde(rename = "BuildLabel", default, skip_serializing_if = "Option::is_none")]
+    pub build_label: Option<String>,
+    /// Changelist the build was cooked from.
+    #[serde(rename = "CL", default, skip_serializing_if = "Option::is_none")]
+    pub changelist: Option<u64>,
+}
+
+impl ManifestFile {
+    /// Catches manifests that parse but describe chunks inconsistently, before any of
+    /// their chunks are downloaded.
+    pub fn validate(&self) -> Result<(), String> {
+        self.chunks.iter().try_for_each(ChunkedFile::validate)
+    }
+}
+
+fn legacy_manifest_version() -> u32 {
+    1
+}
+
+fn supported_manifest_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version == 0 || version > MANIFEST_VERSION {
+        return Err(
+            serde::de::Error::custom(
+                format!(
+                    "manifest version {} is not supported, this launcher reads versions 1 to {}",
+                    version,
+                    MANIFEST_VERSION
+                )
+            )
+        );
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<ManifestFile, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    fn chunked_file(file: &str, chunks_ids: Vec<i32>, file_size: i64) -> ChunkedFile {
+        ChunkedFile {
+            chunks_ids,
+            file: file.to_string(),
+            file_size,
+            chunks_hashes: None,
+            file_hash: None,
+            chunks_compression: None,
+            chunks_sizes: None,
+            chunks_offsets: None,
+        }
+    }
+
+    #[test]
+    fn reads_version_1_manifests() {
+        let manifest = parse(
+            r#"{
+                "Name": "1.0",
+                "Size": 10,
+                "Chunks": [{ "ChunksIds": [1, 2], "File": "Game/a.pak", "FileSize": 10 }]
+            }"#
+        ).unwrap();
+
+        assert_eq!(manifest.manifest_version, 1);
+        assert_eq!(manifest.compression, ChunkCompression::Gzip);
+        let chunk = manifest.chunks[0].chunk(1, manifest.compression);
+        assert_eq!(chunk.id, 2);
+        assert_eq!(chunk.hash, None);
+        assert_eq!(chunk.size, None);
+        assert_eq!(chunk.offset, None);
+        manifest.validate().unwrap();
+    }
+
+    #[test]
+    fn reads_version_2_manifests() {
+        let manifest = parse(
+            r#"{
+                "ManifestVersion": 2,
+                "Name": "2.0",
+                "Size": 10,
+                "Compression": "zstd",
+                "Chunks": [{
+                    "ChunksIds": [1, 2],
+                    "File": "Game/a.pak",
+                    "FileSize": 10,
+                    "ChunksHashes": ["aa", "bb"],
+                    "ChunksCompression": ["none", "gzip"],
+                    "ChunksSizes": [4, 6]
+                }]
+            }"#
+        ).unwrap();
+
+        assert_eq!(manifest.manifest_version, 2);
+        assert_eq!(manifest.compression, ChunkCompression::Zstd);
+        let chunk = manifest.chunks[0].chunk(1, manifest.compression);
+        assert_eq!(chunk.hash.as_deref(), Some("bb"));
+        assert_eq!(chunk.compression, ChunkCompression::Gzip);
+        assert_eq!(chunk.size, Some(6));
+        assert_eq!(chunk.offset, Some(4));
+        manifest.validate().unwrap();
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        for version in [0, MANIFEST_VERSION + 1] {
+            let json = format!(
+                r#"{{ "ManifestVersion": {}, "Name": "x", "Size": 0, "Chunks": [] }}"#,
+                version
+            );
+            let error = parse(&json).unwrap_err().to_string();
+            assert!(error.contains(&format!("manifest version {} is not supported", version)), "{}", error);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_paths_outside_the_install_folder() {
+        for file in ["../evil.dll", "/etc/passwd", "Game/../../evil.dll", ""] {
+            let error = chunked_file(file, vec![1], 1).validate().unwrap_err();
+            assert!(error.contains("is not a path inside the install folder"), "{}", error);
+        }
+        chunked_file("Game/./a.pak", vec![1], 1).validate().unwrap();
+    }
+
+    #[test]
+    fn validate_checks_list_lengths() {
+        let mut file = chunked_file("a.pak", vec![1, 2], 10);
+        file.chunks_hashes = Some(vec!["aa".into()]);
+        assert_eq!(file.validate().unwrap_err(), "ChunksHashes of a.pak has 1 entries for 2 chunks");
+
+        let mut file = chunked_file("a.pak", vec![1, 2], 10);
+        file.chunks_compression = Some(vec![ChunkCompression::None; 3]);
+        assert_eq!(file.validate().unwrap_err(), "ChunksCompression of a.pak has 3 entries for 2 chunks");
+    }
+
+    #[test]
+    fn validate_checks_chunks_cover_the_file() {
+        let mut file = chunked_file("a.pak", vec![1, 2], 10);
+        file.chunks_offsets = Some(vec![0, 4]);
+        assert_eq!(file.validate().unwrap_err(), "a.pak has chunk offsets but no chunk sizes");
+
+        file.chunks_sizes = Some(vec![4, 6]);
+        file.validate().unwrap();
+
+        file.chunks_offsets = Some(vec![0, 5]);
+        assert_eq!(file.validate().unwrap_err(), "Chunk 2 of a.pak starts at byte 5, expected 4");
+
+        file.chunks_offsets = None;
+        file.file_size = 12;
+        assert_eq!(file.validate().unwrap_err(), "Chunks of a.pak add up to 10 bytes but the file is 12 bytes");
+    }
+}
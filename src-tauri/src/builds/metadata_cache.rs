@@ -0,0 +1,58 @@
+use serde::{ Deserialize, Serialize };
+use std::io;
+use std::path::PathBuf;
+
+use super::{ atomic_write_async, file_name_for };
+
+/// A response body kept on disk together with the validators needed to revalidate it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Seconds since the Unix epoch at which a server last confirmed this body.
+    pub fetched_at: u64,
+    pub body: String,
+}
+
+/// Last known copies of the version list and manifests, so the library keeps working
+/// without a connection.
+pub struct MetadataCache {
+    dir: PathBuf,
+}
+
+impl Default for MetadataCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetadataCache {
+    pub fn new() -> Self {
+        let dir = dirs
+            ::data_local_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("com.solarisfn.org")
+            .join("metadata");
+
+        Self::with_dir(dir)
+    }
+
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", file_name_for(key)))
+    }
+
+    pub async fn load(&self, key: &str) -> Option<CachedResponse> {
+        let data = tokio::fs::read(self.entry_path(key)).await.ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    pub async fn store(&self, key: &str, response: &CachedResponse) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+
+        atomic_write_async(&self.entry_path(key), &serde_json::to_vec(response)?).await
+    }
+}
@@ -0,0 +1,256 @@
+use reqwest::Client;
+use serde::Serialize;
+use std::time::{ Duration, Instant };
+use tokio::sync::Mutex;
+
+const MAX_HEALTH: i32 = 10;
+const MIN_HEALTH: i32 = -30;
+const FAILURE_PENALTY: i32 = 3;
+const PROBE_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Clone, Serialize)]
+pub struct MirrorStatus {
+    pub base_url: String,
+    pub health: i32,
+    pub latency_ms: Option<u64>,
+}
+
+struct Mirror {
+    base_url: String,
+    health: i32,
+    latency: Option<Duration>,
+}
+
+/// An ordered list of hosts serving the same content. Mirrors are tried healthiest first,
+/// then fastest, then in the order they were configured.
+pub struct MirrorSet {
+    mirrors: Mutex<Vec<Mirror>>,
+}
+
+impl MirrorSet {
+    pub fn new<S: Into<String>>(base_urls: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            mirrors: Mutex::new(
+                base_urls
+                    .into_iter()
+                    .map(|base_url| Mirror {
+                        base_url: base_url.into().trim_end_matches('/').to_string(),
+                        health: 0,
+                        latency: None,
+                    })
+                    .collect()
+            ),
+        }
+    }
+
+    /// Replaces the configured hosts. Mirrors that stay in the list keep their health and latency.
+    pub async fn set_base_urls(&self, base_urls: &[String]) {
+        let mut mirrors = self.mirrors.lock().await;
+        let mut previous: Vec<Mirror> = mirrors.drain(..).collect();
+
+        for base_url in base_urls {
+            let base_url = base_url.trim_end_matches('/').to_string();
+            let mirror = match previous.iter().position(|mirror| mirror.base_url == base_url) {
+                Some(index) => previous.remove(index),
+                None => Mirror { base_url, health: 0, latency: None },
+            };
+            mirrors.push(mirror);
+        }
+    }
+
+    pub async fn ordered(&self) -> Vec<String> {
+        let mirrors = self.mirrors.lock().await;
+        let mut ordered: Vec<&Mirror> = mirrors.iter().collect();
+        ordered.sort_by(|a, b| {
+            b.health
+                .cmp(&a.health)
+                .then_with(|| a.latency.unwrap_or(Duration::MAX).cmp(&b.latency.unwrap_or(Duration::MAX)))
+        });
+
+        ordered
+            .into_iter()
+            .map(|mirror| mirror.base_url.clone())
+            .collect()
+    }
+
+    pub async fn status(&self) -> Vec<MirrorStatus> {
+        let mirrors = self.mirrors.lock().await;
+        mirrors
+            .iter()
+            .map(|mirror| MirrorStatus {
+                base_url: mirror.base_url.clone(),
+                health: mirror.health,
+                latency_ms: mirror.latency.map(|latency| latency.as_millis() as u64),
+            })
+            .collect()
+    }
+
+    pub async fn record_success(&self, base_url: &str) {
+        let mut mirrors = self.mirrors.lock().await;
+        if let Some(mirror) = mirrors.iter_mut().find(|mirror| mirror.base_url == base_url) {
+            mirror.health = (mirror.health + 1).min(MAX_HEALTH);
+        }
+    }
+
+    pub async fn record_failure(&self, base_url: &str) {
+        let mut mirrors = self.mirrors.lock().await;
+        if let Some(mirror) = mirrors.iter_mut().find(|mirror| mirror.base_url == base_url) {
+            mirror.health = (mirror.health - FAILURE_PENALTY).max(MIN_HEALTH);
+        }
+    }
+
+    /// Measures the round trip of a request for `path` on every mirror. Mirrors that do not
+    /// answer lose health so they sort behind the ones that do.
+    pub async fn probe_latency(&self, client: &Client, path: &str) {
+        let base_urls: Vec<String> = self.mirrors
+            .lock().await
+            .iter()
+            .map(|mirror| mirror.base_url.clone())
+            .collect();
+
+        let probes = base_urls.iter().map(|base_url| async move {
+            let start = Instant::now();
+            let result = client
+                .head(format!("{}/{}", base_url, path))
+                .timeout(Duration::from_secs(PROBE_TIMEOUT_SECS))
+                .send().await;
+
+            let latency = match result {
+                Ok(response) if !response.status().is_server_error() => Some(start.elapsed()),
+                _ => None,
+            };
+            (base_url, latency)
+        });
+
+        for (base_url, latency) in futures::future::join_all(probes).await {
+            let mut mirrors = self.mirrors.lock().await;
+            if let Some(mirror) = mirrors.iter_mut().find(|mirror| &mirror.base_url == base_url) {
+                mirror.latency = latency;
+                if latency.is_none() {
+                    mirror.health = (mirror.health - FAILURE_PENALTY).max(MIN_HEALTH);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+    use tokio::net::TcpListener;
+
+    /// Serves every request on a local port with `status` after `delay` and returns its base URL.
+    async fn serve(status: &'static str, delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 1024];
+                    let _ = socket.read(&mut request).await;
+                    tokio::time::sleep(delay).await;
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        base_url
+    }
+
+    /// A base URL nothing listens on, so connecting to it is refused.
+    async fn refused() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    fn client() -> Client {
+        Client::builder().no_proxy().build().unwrap()
+    }
+
+    async fn health(mirrors: &MirrorSet, base_url: &str) -> i32 {
+        mirrors
+            .status().await
+            .into_iter()
+            .find(|status| status.base_url == base_url)
+            .unwrap().health
+    }
+
+    #[tokio::test]
+    async fn orders_by_health_then_configuration() {
+        let mirrors = MirrorSet::new(["https://a.test/", "https://b.test", "https://c.test"]);
+        assert_eq!(mirrors.ordered().await, ["https://a.test", "https://b.test", "https://c.test"]);
+
+        mirrors.record_success("https://c.test").await;
+        mirrors.record_failure("https://a.test").await;
+        assert_eq!(mirrors.ordered().await, ["https://c.test", "https://b.test", "https://a.test"]);
+    }
+
+    #[tokio::test]
+    async fn health_is_bounded() {
+        let mirrors = MirrorSet::new(["https://a.test", "https://b.test"]);
+        for _ in 0..100 {
+            mirrors.record_success("https://a.test").await;
+            mirrors.record_failure("https://b.test").await;
+        }
+        assert_eq!(health(&mirrors, "https://a.test").await, MAX_HEALTH);
+        assert_eq!(health(&mirrors, "https://b.test").await, MIN_HEALTH);
+    }
+
+    #[tokio::test]
+    async fn probe_fails_over_from_server_errors_and_refused_connections() {
+        let broken = serve("500 Internal Server Error", Duration::ZERO).await;
+        let down = refused().await;
+        let slow = serve("200 OK", Duration::from_millis(300)).await;
+        let fast = serve("200 OK", Duration::ZERO).await;
+
+        let mirrors = MirrorSet::new([broken.clone(), down.clone(), slow.clone(), fast.clone()]);
+        mirrors.probe_latency(&client(), "versions.json").await;
+
+        assert_eq!(mirrors.ordered().await, [fast.clone(), slow.clone(), broken.clone(), down.clone()]);
+        assert_eq!(health(&mirrors, &broken).await, -FAILURE_PENALTY);
+        assert_eq!(health(&mirrors, &down).await, -FAILURE_PENALTY);
+
+        let status = mirrors.status().await;
+        assert!(status.iter().find(|status| status.base_url == broken).unwrap().latency_ms.is_none());
+        assert!(status.iter().find(|status| status.base_url == fast).unwrap().latency_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn failed_mirror_recovers_after_successes() {
+        let mirrors = MirrorSet::new(["https://a.test", "https://b.test"]);
+        mirrors.record_failure("https://a.test").await;
+        assert_eq!(mirrors.ordered().await[0], "https://b.test");
+
+        for _ in 0..FAILURE_PENALTY {
+            mirrors.record_success("https://a.test").await;
+        }
+        assert_eq!(mirrors.ordered().await[0], "https://a.test");
+
+        // A mirror that answers again gets its latency back on the next probe
+        let up = serve("200 OK", Duration::ZERO).await;
+        let mirrors = MirrorSet::new([up.clone()]);
+        mirrors.record_failure(&up).await;
+        mirrors.probe_latency(&client(), "").await;
+        assert!(mirrors.status().await[0].latency_ms.is_some());
+        assert_eq!(health(&mirrors, &up).await, -FAILURE_PENALTY);
+        mirrors.record_success(&up).await;
+        assert_eq!(health(&mirrors, &up).await, 1 - FAILURE_PENALTY);
+    }
+
+    #[tokio::test]
+    async fn replacing_base_urls_keeps_known_health() {
+        let mirrors = MirrorSet::new(["https://a.test", "https://b.test"]);
+        mirrors.record_success("https://b.test").await;
+
+        mirrors.set_base_urls(&["https://c.test".to_string(), "https://b.test/".to_string()]).await;
+        assert_eq!(mirrors.ordered().await, ["https://b.test", "https://c.test"]);
+        assert_eq!(health(&mirrors, "https://b.test").await, 1);
+        assert_eq!(health(&mirrors, "https://c.test").await, 0);
+    }
+}
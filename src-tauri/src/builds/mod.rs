@@ -1 +1,179 @@
+pub mod chunk_cache;
 pub mod download_manager;
+pub mod extract;
+pub mod hash;
+pub mod history;
+pub mod jobs;
+pub mod journal;
+pub mod manifest;
+pub mod metadata_cache;
+pub mod mirrors;
+pub mod preflight;
+pub mod reuse;
+pub mod source;
+pub mod throttle;
+
+use std::io;
+use std::path::{ Component, Path, PathBuf };
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+/// Turns a build ID, version or cache key into a name that is safe to use as a file name on
+/// every platform.
+pub fn file_name_for(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+/// Turns a path from an archive or manifest into one that stays below the folder it is joined
+/// onto. Absolute paths, drive prefixes and `..` are refused rather than stripped.
+pub fn enclosed_path(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    let mut enclosed = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => enclosed.push(part),
+            Component::CurDir => {}
+            _ => {
+                return None;
+            }
+        }
+    }
+
+    (!enclosed.as_os_str().is_empty()).then_some(enclosed)
+}
+
+/// Joins the manifest path `file` onto `base` for writing or deleting it. Besides refusing
+/// paths that lead out of `base` on their own, the deepest existing folder on the way is
+/// resolved so a symlink or junction inside the install cannot redirect the write elsewhere.
+pub async fn contained_path(base: &Path, file: &str) -> io::Result<PathBuf> {
+    let outside = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} points outside of {}", file, base.display())
+        )
+    };
+    let path = base.join(enclosed_path(file).ok_or_else(outside)?);
+
+    let resolved_base = match tokio::fs::canonicalize(base).await {
+        Ok(resolved_base) => resolved_base,
+        // Nothing below a folder that does not exist yet can lead out of it
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(path);
+        }
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    let mut existing = path.as_path();
+    let resolved = loop {
+        match tokio::fs::canonicalize(existing).await {
+            Ok(resolved) => break resolved,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                existing = existing.parent().ok_or(e)?;
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    };
+
+    if resolved.starts_with(&resolved_base) { Ok(path) } else { Err(outside()) }
+}
+
+/// A temporary file next to `path` that no other write, from this process or another one,
+/// is using at the same time.
+fn temp_path_for(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}.tmp", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    path.with_file_name(name)
+}
+
+/// Writes `data` to a temporary file next to `path` and renames it into place, so a crash
+/// mid-save never leaves a truncated file behind. Concurrent writes to the same path each
+/// use their own temporary file and the last rename wins.
+pub fn atomic_write(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp_path = temp_path_for(path);
+    let result = std::fs::write(&temp_path, data).and_then(|()| std::fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// [`atomic_write`] for async callers.
+pub async fn atomic_write_async(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp_path = temp_path_for(path);
+    let mut result = tokio::fs::write(&temp_path, data).await;
+    if result.is_ok() {
+        result = tokio::fs::rename(&temp_path, path).await;
+    }
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enclosed_path_refuses_leaving_the_folder() {
+        assert_eq!(enclosed_path("FortniteGame/Content/Paks/a.pak"), Some(PathBuf::from("FortniteGame/Content/Paks/a.pak")));
+        assert_eq!(enclosed_path("./Engine/b.dll"), Some(PathBuf::from("Engine/b.dll")));
+
+        for name in ["", ".", "/etc/passwd", "../outside.dll", "Engine/../../outside.dll"] {
+            assert_eq!(enclosed_path(name), None, "{:?} was accepted", name);
+        }
+        #[cfg(windows)]
+        for name in ["C:\\Windows\\System32\\a.dll", "C:a.dll", "\\\\server\\share\\a.dll", "..\\a.dll"] {
+            assert_eq!(enclosed_path(name), None, "{:?} was accepted", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_atomic_writes_do_not_collide() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+
+        let writes = (0..16).map(|index| {
+            let path = path.clone();
+            tokio::spawn(async move { atomic_write_async(&path, format!("write {}", index).as_bytes()).await })
+        });
+        for write in futures::future::join_all(writes).await {
+            write.unwrap().unwrap();
+        }
+        atomic_write(&path, b"last write").unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"last write");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn contained_path_stays_below_the_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("install");
+
+        // A base that does not exist yet only needs the lexical check
+        assert_eq!(contained_path(&base, "Engine/a.dll").await.unwrap(), base.join("Engine/a.dll"));
+        assert!(contained_path(&base, "../a.dll").await.is_err());
+
+        std::fs::create_dir_all(base.join("Engine")).unwrap();
+        assert_eq!(contained_path(&base, "Engine/a.dll").await.unwrap(), base.join("Engine/a.dll"));
+        assert_eq!(contained_path(&base, "New/Folder/b.dll").await.unwrap(), base.join("New/Folder/b.dll"));
+
+        #[cfg(unix)]
+        {
+            let outside = dir.path().join("outside");
+            std::fs::create_dir_all(&outside).unwrap();
+            std::os::unix::fs::symlink(&outside, base.join("Link")).unwrap();
+            assert!(contained_path(&base, "Link/a.dll").await.is_err());
+            assert!(contained_path(&base, "Link/New/a.dll").await.is_err());
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use sysinfo::{ DiskExt, System, SystemExt };
+
+const FAT32_MAX_FILE_BYTES: u64 = 4 * 1024 * 1024 * 1024 - 1;
+const FAT_FILE_SYSTEMS: [&str; 4] = ["fat", "fat32", "vfat", "msdos"];
+#[cfg(windows)]
+const MAX_PATH_LENGTH: usize = 259;
+#[cfg(not(windows))]
+const MAX_PATH_LENGTH: usize = 4095;
+
+/// What an install is going to write, as far as it is known before the download starts.
+pub struct InstallRequirements {
+    /// Final size of the install, `None` when the server did not say.
+    pub total_bytes: Option<u64>,
+    /// Bytes a resumed download already has on disk.
+    pub written_bytes: u64,
+    /// Extra room needed while the download runs, such as an archive next to its extracted files.
+    pub temp_bytes: u64,
+    /// Room the chunk cache in `cache_path` can still claim while the download runs. It is
+    /// only counted when the cache is on the same volume as the install.
+    pub cache_bytes: u64,
+    pub cache_path: Option<PathBuf>,
+    pub largest_file_bytes: u64,
+    /// Longest path that will be created below the install path, temporary suffixes included.
+    pub longest_relative_path: usize,
+}
+
+#[derive(Serialize)]
+pub struct PreflightCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct PreflightReport {
+    pub passed: bool,
+    pub install_path: String,
+    pub required_bytes: Option<u64>,
+    pub available_bytes: Option<u64>,
+    pub file_system: Option<String>,
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    pub fn failure_message(&self) -> String {
+        let failures: Vec<&str> = self.checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.message.as_str())
+            .collect();
+
+        format!("Preflight check failed: {}", failures.join("; "))
+    }
+}
+
+/// Checks that `install_path` can hold an install described by `requirements`. This touches
+/// the disk and enumerates volumes, so async callers should run it on a blocking thread.
+pub fn check(install_path: &Path, requirements: &InstallRequirements) -> PreflightReport {
+    let install_directory = existing_ancestor(install_path);
+    let disk = install_directory.as_deref().and_then(disk_for_path);
+
+    let cache_disk = requirements.cache_path
+        .as_deref()
+        .and_then(existing_ancestor)
+        .as_deref()
+        .and_then(disk_for_path);
+    let cache_bytes = cache_bytes_on(disk.as_ref(), cache_disk.as_ref(), requirements.cache_bytes);
+
+    let required_bytes = requirements.total_bytes.map(|total_bytes| {
+        total_bytes.saturating_sub(requirements.written_bytes) + requirements.temp_bytes + cache_bytes
+    });
+    let available_bytes = disk.as_ref().map(|disk| disk.available_bytes);
+    let file_system = disk.map(|disk| disk.file_system);
+
+    let mut checks = Vec::new();
+
+    checks.push(match (required_bytes, available_bytes) {
+        (Some(required), Some(available)) =>
+            PreflightCheck {
+                name: "disk_space",
+                passed: available >= required,
+                message: if cache_bytes > 0 {
+                    format!(
+                        "{} required, {} of it for the chunk cache, {} available",
+                        format_bytes(required),
+                        format_bytes(cache_bytes),
+                        format_bytes(available)
+                    )
+                } else {
+                    format!("{} required, {} available", format_bytes(required), format_bytes(available))
+                },
+            },
+        (None, _) =>
+            PreflightCheck {
+                name: "disk_space",
+                passed: true,
+                message: "Download size is unknown, free space was not checked".to_string(),
+            },
+        (Some(_), None) =>
+            PreflightCheck {
+                name: "disk_space",
+                passed: true,
+                message: "Could not determine the volume of the install path".to_string(),
+            },
+    });
+
+    checks.push(match &install_directory {
+        Some(directory) =>
+            match probe_write(directory) {
+                Ok(()) =>
+                    PreflightCheck {
+                        name: "write_permission",
+                        passed: true,
+                        message: format!("{} is writable", directory.display()),
+                    },
+                Err(e) =>
+                    PreflightCheck {
+                        name: "write_permission",
+                        passed: false,
+                        message: format!("Cannot write to {}: {}", directory.display(), e),
+                    },
+            }
+        None =>
+            PreflightCheck {
+                name: "write_permission",
+                passed: false,
+                message: format!("No existing parent directory for {}", install_path.display()),
+            },
+    });
+
+    let longest_path =
+        install_path.to_string_lossy().len() +
+        (if requirements.longest_relative_path > 0 {
+            requirements.longest_relative_path + 1
+        } else {
+            0
+        });
+    checks.push(PreflightCheck {
+        name: "path_length",
+        passed: longest_path <= MAX_PATH_LENGTH,
+        message: format!(
+            "Longest path is {} characters, the limit is {}",
+            longest_path,
+            MAX_PATH_LENGTH
+        ),
+    });
+
+    let fat = file_system
+        .as_deref()
+        .is_some_and(|file_system| FAT_FILE_SYSTEMS.contains(&file_system.to_ascii_lowercase().as_str()));
+    checks.push(if fat && requirements.largest_file_bytes > FAT32_MAX_FILE_BYTES {
+        PreflightCheck {
+            name: "file_size_limit",
+            passed: false,
+            message: format!(
+                "{} cannot store files over 4 GB, the largest file is {}",
+                file_system.as_deref().unwrap_or_default(),
+                format_bytes(requirements.largest_file_bytes)
+            ),
+        }
+    } else {
+        PreflightCheck {
+            name: "file_size_limit",
+            passed: true,
+            message: format!("Largest file is {}", format_bytes(requirements.largest_file_bytes)),
+        }
+    });
+
+    PreflightReport {
+        passed: checks.iter().all(|check| check.passed),
+        install_path: install_path.to_string_lossy().to_string(),
+        required_bytes,
+        available_bytes,
+        file_system,
+        checks,
+    }
+}
+
+struct DiskInfo {
+    mount_point: PathBuf,
+    available_bytes: u64,
+    file_system: String,
+}
+
+/// How much of `cache_bytes` the chunk cache writes to the install volume, nothing when either
+/// volume is unknown or the cache lives elsewhere.
+fn cache_bytes_on(install_disk: Option<&DiskInfo>, cache_disk: Option<&DiskInfo>, cache_bytes: u64) -> u64 {
+    match (install_disk, cache_disk) {
+        (Some(install_disk), Some(cache_disk)) if install_disk.mount_point == cache_disk.mount_point => cache_bytes,
+        _ => 0,
+    }
+}
+
+/// The closest directory at or above `path` that already exists.
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Finds the volume holding `path` by picking the disk with the longest matching mount point.
+fn disk_for_path(path: &Path) -> Option<DiskInfo> {
+    let path = fs::canonicalize(path).ok()?;
+    // Canonical paths on Windows carry a verbatim prefix that mount points do not have
+    let path = PathBuf::from(path.to_string_lossy().trim_start_matches(r"\\?\"));
+
+    let mut system = System::new();
+    system.refresh_disks_list();
+
+    system
+        .disks()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| DiskInfo {
+            mount_point: disk.mount_point().to_path_buf(),
+            available_bytes: disk.available_space(),
+            file_system: String::from_utf8_lossy(disk.file_system()).to_string(),
+        })
+}
+
+fn probe_write(directory: &Path) -> std::io::Result<()> {
+    let probe_path = directory.join(format!(".solaris-preflight-{}", std::process::id()));
+    fs::write(&probe_path, b"")?;
+    fs::remove_file(&probe_path)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.2} {}", size, UNITS[unit]) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(mount_point: &str) -> DiskInfo {
+        DiskInfo {
+            mount_point: PathBuf::from(mount_point),
+            available_bytes: 0,
+            file_system: "ext4".into(),
+        }
+    }
+
+    #[test]
+    fn counts_the_chunk_cache_only_on_the_install_volume() {
+        assert_eq!(cache_bytes_on(Some(&disk("/")), Some(&disk("/")), 512), 512);
+        assert_eq!(cache_bytes_on(Some(&disk("/games")), Some(&disk("/")), 512), 0);
+        assert_eq!(cache_bytes_on(Some(&disk("/")), None, 512), 0);
+        assert_eq!(cache_bytes_on(None, Some(&disk("/")), 512), 0);
+    }
+}
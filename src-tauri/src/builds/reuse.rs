@@ -0,0 +1,177 @@
+use std::collections::{ HashMap, HashSet };
+use std::path::{ Path, PathBuf };
+
+use super::chunk_cache::copy_range;
+use super::contained_path;
+use super::manifest::{ ChunkRef, ManifestFile };
+
+/// A chunk of the installed version, inside a file that is already on disk.
+#[derive(Clone)]
+pub struct LocalChunk {
+    file: String,
+    path: PathBuf,
+    offset: u64,
+    size: u64,
+}
+
+impl LocalChunk {
+    /// Copies the chunk into `dest` at `offset` and returns its size, or `None` when the file
+    /// no longer holds what the manifest says it does. When `chunk` has a hash the copy is
+    /// checked against it.
+    pub async fn copy_to(self, chunk: &ChunkRef, dest: &Path, offset: u64) -> Option<u64> {
+        let expected_size = self.size;
+        let copied = {
+            let (dest, hashes) = (dest.to_path_buf(), chunk.hash.is_some());
+            tokio::task
+                ::spawn_blocking(move || copy_range(&self.path, self.offset, Some(self.size), &dest, offset, hashes)).await
+        };
+        let Ok(Ok((size, actual_hash))) = copied else {
+            return None;
+        };
+
+        let hash_matches = match (&chunk.hash, actual_hash) {
+            (Some(expected), Some(actual)) => expected.eq_ignore_ascii_case(&actual),
+            _ => true,
+        };
+        (size == expected_size && hash_matches).then_some(size)
+    }
+}
+
+/// The chunks of an installed version, so an update can copy the ones the new version still
+/// uses out of the install instead of downloading them again.
+#[derive(Default)]
+pub struct LocalChunks {
+    chunks: HashMap<i32, Vec<LocalChunk>>,
+    /// Files the update replaces.
+    rewritten: HashSet<String>,
+}
+
+impl LocalChunks {
+    /// Indexes the chunks `manifest` places in the files under `base_path`. Only files whose
+    /// manifest entry lists chunk sizes and whose length on disk matches it are used.
+    pub async fn index(base_path: &Path, manifest: &ManifestFile, rewritten: HashSet<String>) -> Self {
+        let mut chunks: HashMap<i32, Vec<LocalChunk>> = HashMap::new();
+
+        for chunked_file in &manifest.chunks {
+            let Some(sizes) = &chunked_file.chunks_sizes else {
+                continue;
+            };
+            let Ok(path) = contained_path(base_path, &chunked_file.file).await else {
+                continue;
+            };
+            let complete = tokio::fs
+                ::metadata(&path).await
+                .is_ok_and(|metadata| metadata.len() as i64 == chunked_file.file_size);
+            if !complete {
+                continue;
+            }
+
+            for (index, (&id, &size)) in chunked_file.chunks_ids.iter().zip(sizes).enumerate() {
+                let Some(offset) = chunked_file.chunk_offset(index) else {
+                    continue;
+                };
+                chunks.entry(id).or_default().push(LocalChunk {
+                    file: chunked_file.file.clone(),
+                    path: path.clone(),
+                    offset,
+                    size,
+                });
+            }
+        }
+
+        Self { chunks, rewritten }
+    }
+
+    /// Where a copy of `chunk` can be read while `file` is rebuilt. A rewritten file is
+    /// replaced once its own `.part` file is complete, possibly while the chunks of later
+    /// files are still being written, so it only serves chunks to itself.
+    pub fn find(&self, file: &str, chunk: &ChunkRef) -> Option<LocalChunk> {
+        self.chunks
+            .get(&chunk.id)?
+            .iter()
+            .find(|local| {
+                (local.file == file || !self.rewritten.contains(&local.file)) &&
+                    chunk.size.is_none_or(|size| size == local.size)
+            })
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builds::manifest::ChunkCompression;
+    use std::fs;
+
+    // SHA-256 of the second chunk of Game/a.pak
+    const B_HASH: &str = "4625fd63b0e96fc0d656ae7381605e48d4a0f63a319fc743adf22688613883c7";
+
+    fn chunk(id: i32, size: Option<u64>, hash: Option<&str>) -> ChunkRef {
+        ChunkRef {
+            id,
+            hash: hash.map(str::to_string),
+            compression: ChunkCompression::Gzip,
+            size,
+            offset: None,
+        }
+    }
+
+    /// An install of a version with two files, the first of which the update rewrites.
+    async fn install() -> (LocalChunks, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("Game")).unwrap();
+        fs::write(dir.path().join("Game/a.pak"), b"aaaabbbbbb").unwrap();
+        fs::write(dir.path().join("Game/b.pak"), b"ccccc").unwrap();
+        fs::write(dir.path().join("Game/c.pak"), b"dd").unwrap();
+
+        let manifest: ManifestFile = serde_json
+            ::from_str(
+                r#"{
+                    "ManifestVersion": 2,
+                    "Name": "1.0",
+                    "Size": 18,
+                    "Chunks": [
+                        { "ChunksIds": [1, 2], "File": "Game/a.pak", "FileSize": 10, "ChunksSizes": [4, 6] },
+                        { "ChunksIds": [3], "File": "Game/b.pak", "FileSize": 5, "ChunksSizes": [5] },
+                        { "ChunksIds": [4], "File": "Game/c.pak", "FileSize": 3, "ChunksSizes": [3] }
+                    ]
+                }"#
+            )
+            .unwrap();
+        let rewritten = HashSet::from(["Game/a.pak".to_string()]);
+
+        (LocalChunks::index(dir.path(), &manifest, rewritten).await, dir)
+    }
+
+    #[tokio::test]
+    async fn rewritten_files_only_serve_themselves() {
+        let (local, _dir) = install().await;
+
+        let found = local.find("Game/a.pak", &chunk(2, Some(6), None)).unwrap();
+        assert_eq!((found.offset, found.size), (4, 6));
+        assert!(local.find("Game/new.pak", &chunk(2, Some(6), None)).is_none());
+        assert!(local.find("Game/new.pak", &chunk(3, None, None)).is_some());
+    }
+
+    #[tokio::test]
+    async fn skips_damaged_files_and_other_sizes() {
+        let (local, _dir) = install().await;
+
+        assert!(local.find("Game/new.pak", &chunk(3, Some(4), None)).is_none());
+        // Game/c.pak is shorter than its manifest entry
+        assert!(local.find("Game/c.pak", &chunk(4, None, None)).is_none());
+    }
+
+    #[tokio::test]
+    async fn copies_chunks_that_match_their_hash() {
+        let (local, dir) = install().await;
+        let dest = dir.path().join("Game/a.pak.part");
+
+        let found = local.find("Game/a.pak", &chunk(2, Some(6), None)).unwrap();
+        assert_eq!(found.copy_to(&chunk(2, Some(6), Some(B_HASH)), &dest, 4).await, Some(6));
+        assert_eq!(fs::read(&dest).unwrap(), b"\0\0\0\0bbbbbb");
+
+        let found = local.find("Game/a.pak", &chunk(1, Some(4), None)).unwrap();
+        assert_eq!(found.copy_to(&chunk(1, Some(4), Some(B_HASH)), &dest, 0).await, None);
+    }
+}
@@ -0,0 +1,104 @@
+use reqwest::Url;
+use std::path::{ Path, PathBuf };
+
+/// Where a manifest install reads its manifest and chunks from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChunkSource {
+    /// The configured manifest mirrors.
+    Mirrors,
+    /// A single server laid out like a mirror, such as one on the local network.
+    Http(String),
+    /// A local folder, USB drive or network share holding `<version>.manifest` and the
+    /// `.chunk` files.
+    Directory(PathBuf),
+}
+
+impl ChunkSource {
+    /// Parses the `source` of a download request: an http(s) base URL, a file:// URL or a
+    /// directory path. No source means the configured mirrors.
+    pub fn parse(source: Option<&str>) -> Result<Self, String> {
+        let Some(source) = source.map(str::trim).filter(|source| !source.is_empty()) else {
+            return Ok(Self::Mirrors);
+        };
+
+        let lower = source.to_ascii_lowercase();
+        if lower.starts_with("http://") || lower.starts_with("https://") {
+            Ok(Self::Http(source.trim_end_matches('/').to_string()))
+        } else if lower.starts_with("file:") {
+            let url = Url::parse(source).map_err(|e| format!("Invalid file URL {}: {}", source, e))?;
+            url.to_file_path()
+                .map(Self::Directory)
+                .map_err(|_| format!("{} does not point to a local path", source))
+        } else {
+            Ok(Self::Directory(PathBuf::from(source)))
+        }
+    }
+}
+
+/// The folder holding the manifest and chunks of `version` (such as `13.40`) below `root`.
+/// A source can point straight at it or at a folder laid out like a mirror, with one
+/// subfolder per version.
+pub async fn version_dir(root: &Path, version: &str) -> PathBuf {
+    let nested = root.join(version);
+    let nested_manifest = nested.join(format!("{}.manifest", version));
+    if tokio::fs::metadata(&nested_manifest).await.is_ok_and(|metadata| metadata.is_file()) {
+        nested
+    } else {
+        root.to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<ChunkSource, String> {
+        ChunkSource::parse(Some(source))
+    }
+
+    #[test]
+    fn no_source_means_the_mirrors() {
+        assert_eq!(ChunkSource::parse(None), Ok(ChunkSource::Mirrors));
+        assert_eq!(parse("  "), Ok(ChunkSource::Mirrors));
+    }
+
+    #[test]
+    fn parses_http_sources() {
+        assert_eq!(parse("http://192.168.1.20:8080/"), Ok(ChunkSource::Http("http://192.168.1.20:8080".into())));
+        assert_eq!(parse(" HTTPS://mirror.lan/builds "), Ok(ChunkSource::Http("HTTPS://mirror.lan/builds".into())));
+    }
+
+    #[test]
+    fn parses_directories() {
+        assert_eq!(parse("/mnt/usb/Solaris"), Ok(ChunkSource::Directory(PathBuf::from("/mnt/usb/Solaris"))));
+        assert_eq!(parse("builds"), Ok(ChunkSource::Directory(PathBuf::from("builds"))));
+        assert_eq!(parse(r"D:\Solaris\Builds"), Ok(ChunkSource::Directory(PathBuf::from(r"D:\Solaris\Builds"))));
+        assert_eq!(parse(r"\\nas\builds"), Ok(ChunkSource::Directory(PathBuf::from(r"\\nas\builds"))));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn parses_file_urls() {
+        assert_eq!(
+            parse("file:///mnt/usb/Solaris%20Builds"),
+            Ok(ChunkSource::Directory(PathBuf::from("/mnt/usb/Solaris Builds")))
+        );
+        assert_eq!(parse("FILE:///srv/builds/"), Ok(ChunkSource::Directory(PathBuf::from("/srv/builds/"))));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn parses_file_urls() {
+        assert_eq!(
+            parse("file:///D:/Solaris%20Builds"),
+            Ok(ChunkSource::Directory(PathBuf::from(r"D:\Solaris Builds")))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_file_urls() {
+        assert!(parse("file://[invalid/builds").unwrap_err().starts_with("Invalid file URL"));
+        #[cfg(not(windows))]
+        assert!(parse("file://host/builds").unwrap_err().ends_with("does not point to a local path"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+struct TokenBucket {
+    bytes_per_second: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            tokens: bytes_per_second as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Credits the tokens earned since the last refill at the current rate.
+    fn refill(&mut self) {
+        let rate = self.bytes_per_second as f64;
+        let now = Instant::now();
+        let refill = now.duration_since(self.last_refill).as_secs_f64() * rate;
+        self.tokens = (self.tokens + refill).min(rate);
+        self.last_refill = now;
+    }
+
+    /// Switches to `bytes_per_second`, keeping what was earned or owed at the old rate.
+    fn set_rate(&mut self, bytes_per_second: u64) {
+        self.refill();
+        self.bytes_per_second = bytes_per_second;
+        self.tokens = self.tokens.min(bytes_per_second as f64);
+    }
+
+    /// Takes `bytes` out of the bucket and returns how long the caller has to wait for the
+    /// bucket to pay off the debt. The bucket holds at most one second worth of tokens.
+    fn reserve(&mut self, bytes: u64) -> Duration {
+        if self.bytes_per_second == 0 {
+            return Duration::ZERO;
+        }
+
+        self.refill();
+        self.tokens -= bytes as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_second as f64)
+        }
+    }
+}
+
+pub struct BandwidthLimiter {
+    global: Mutex<TokenBucket>,
+    downloads: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BandwidthLimiter {
+    pub fn new() -> Self {
+        Self {
+            global: Mutex::new(TokenBucket::new(0)),
+            downloads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn global_limit(&self) -> Option<u64> {
+        let bytes_per_second = self.global.lock().await.bytes_per_second;
+        (bytes_per_second > 0).then_some(bytes_per_second)
+    }
+
+    pub async fn set_global_limit(&self, bytes_per_second: Option<u64>) {
+        self.global.lock().await.set_rate(bytes_per_second.unwrap_or(0));
+    }
+
+    pub async fn download_limit(&self, build_id: &str) -> Option<u64> {
+        self.downloads
+            .lock().await
+            .get(build_id)
+            .map(|bucket| bucket.bytes_per_second)
+    }
+
+    pub async fn set_download_limit(&self, build_id: &str, bytes_per_second: Option<u64>) {
+        let mut downloads = self.downloads.lock().await;
+        match bytes_per_second.filter(|limit| *limit > 0) {
+            Some(limit) =>
+                match downloads.get_mut(build_id) {
+                    Some(bucket) => bucket.set_rate(limit),
+                    None => {
+                        downloads.insert(build_id.to_string(), TokenBucket::new(limit));
+                    }
+                }
+            None => {
+                downloads.remove(build_id);
+            }
+        }
+    }
+
+    /// Waits until both the global bucket and the bucket of `build_id` allow `bytes` more
+    /// to be read. Downloads without their own limit only share the global one.
+    pub async fn acquire(&self, build_id: &str, bytes: u64) {
+        let global_wait = self.global.lock().await.reserve(bytes);
+        let download_wait = self.downloads
+            .lock().await
+            .get_mut(build_id)
+            .map_or(Duration::ZERO, |bucket| bucket.reserve(bytes));
+
+        let wait = global_wait.max(download_wait);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u64 = 1000;
+
+    /// Runs `acquire` and returns how long it waited on the paused test clock.
+    async fn timed_acquire(limiter: &BandwidthLimiter, build_id: &str, bytes: u64) -> Duration {
+        let start = Instant::now();
+        limiter.acquire(build_id, bytes).await;
+        start.elapsed()
+    }
+
+    fn assert_waited(waited: Duration, expected_ms: u64) {
+        // The paused clock jumps straight to the timer, which rounds up to the next millisecond
+        let expected = Duration::from_millis(expected_ms);
+        assert!(
+            waited >= expected && waited <= expected + Duration::from_millis(1),
+            "waited {:?}, expected {:?}",
+            waited,
+            expected
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn no_limit_never_waits() {
+        let limiter = BandwidthLimiter::new();
+        assert_eq!(limiter.global_limit().await, None);
+        assert_waited(timed_acquire(&limiter, "build", 10 * 1024 * 1024 * 1024).await, 0);
+
+        limiter.set_global_limit(Some(0)).await;
+        limiter.set_download_limit("build", Some(0)).await;
+        assert_eq!(limiter.global_limit().await, None);
+        assert_eq!(limiter.download_limit("build").await, None);
+        assert_waited(timed_acquire(&limiter, "build", 10 * 1024 * 1024 * 1024).await, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn global_limit_paces_reads() {
+        let limiter = BandwidthLimiter::new();
+        limiter.set_global_limit(Some(RATE)).await;
+
+        // The global bucket starts empty when a limit is first set
+        assert_waited(timed_acquire(&limiter, "build", RATE).await, 1000);
+        assert_waited(timed_acquire(&limiter, "build", RATE / 2).await, 500);
+        assert_waited(timed_acquire(&limiter, "other", RATE).await, 1000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn download_limit_only_applies_to_its_download() {
+        let limiter = BandwidthLimiter::new();
+        limiter.set_download_limit("slow", Some(RATE)).await;
+
+        assert_waited(timed_acquire(&limiter, "slow", RATE).await, 0);
+        assert_waited(timed_acquire(&limiter, "slow", RATE).await, 1000);
+        assert_waited(timed_acquire(&limiter, "fast", 100 * RATE).await, 0);
+
+        limiter.set_download_limit("slow", None).await;
+        assert_waited(timed_acquire(&limiter, "slow", 100 * RATE).await, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_change_applies_to_the_running_download() {
+        let limiter = BandwidthLimiter::new();
+        limiter.set_download_limit("build", Some(RATE)).await;
+        assert_waited(timed_acquire(&limiter, "build", RATE).await, 0);
+
+        limiter.set_download_limit("build", Some(4 * RATE)).await;
+        assert_eq!(limiter.download_limit("build").await, Some(4 * RATE));
+        assert_waited(timed_acquire(&limiter, "build", 2 * RATE).await, 500);
+
+        // Time before the change is credited at the old rate, capped to the new one
+        tokio::time::advance(Duration::from_secs(10)).await;
+        limiter.set_download_limit("build", Some(RATE / 10)).await;
+        assert_waited(timed_acquire(&limiter, "build", RATE / 10).await, 0);
+        assert_waited(timed_acquire(&limiter, "build", RATE / 10).await, 1000);
+    }
+}
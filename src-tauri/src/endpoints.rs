@@ -0,0 +1,220 @@
+use reqwest::Url;
+use serde::{ Deserialize, Serialize };
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{ State, command };
+
+use crate::builds::atomic_write;
+use crate::builds::download_manager::DownloadManager;
+use crate::error::LauncherError;
+
+const ENDPOINTS_FILE: &str = "endpoints.json";
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Endpoints {
+    pub manifest_mirrors: Vec<String>,
+    pub version_mirrors: Vec<String>,
+    pub asteria_dll_url: String,
+    pub ip_lookup_url: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            manifest_mirrors: vec!["https://manifest.simplyblk.xyz".to_string()],
+            version_mirrors: vec!["https://cdn.solarisfn.dev".to_string()],
+            asteria_dll_url: "https://cdn.asteria-ac.xyz/Asteria.vmp.dll".to_string(),
+            ip_lookup_url: "https://api4.ipify.org".to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Environment variables win over the settings file so a staging backend can be
+    /// selected for a single run without touching the user's configuration. Variables that
+    /// do not hold valid URLs are ignored.
+    fn with_env_overrides(mut self) -> Self {
+        if let Some(urls) = env_list("SOLARIS_MANIFEST_URLS") {
+            self.manifest_mirrors = urls;
+        }
+        if let Some(urls) = env_list("SOLARIS_VERSION_URLS") {
+            self.version_mirrors = urls;
+        }
+        if let Some(url) = env_url("SOLARIS_ASTERIA_DLL_URL") {
+            self.asteria_dll_url = url;
+        }
+        if let Some(url) = env_url("SOLARIS_IP_LOOKUP_URL") {
+            self.ip_lookup_url = url;
+        }
+        self
+    }
+
+    /// Rejects settings the launcher cannot work with: an empty mirror list or anything that is
+    /// not an http(s) URL.
+    fn validate(&self) -> Result<(), LauncherError> {
+        for (name, mirrors) in [
+            ("manifest", &self.manifest_mirrors),
+            ("version", &self.version_mirrors),
+        ] {
+            if mirrors.is_empty() {
+                return Err(LauncherError::InvalidRequest {
+                    message: format!("At least one {} mirror is required", name),
+                });
+            }
+            for url in mirrors {
+                validate_url(url)?;
+            }
+        }
+
+        validate_url(&self.asteria_dll_url)?;
+        validate_url(&self.ip_lookup_url)
+    }
+}
+
+fn validate_url(url: &str) -> Result<(), LauncherError> {
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => Ok(()),
+        _ => Err(LauncherError::InvalidRequest { message: format!("{} is not a valid http(s) URL", url) }),
+    }
+}
+
+fn env_list(name: &str) -> Option<Vec<String>> {
+    let value = std::env::var(name).ok()?;
+    let urls: Vec<String> = value
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+
+    (!urls.is_empty() && urls.iter().all(|url| validate_url(url).is_ok())).then_some(urls)
+}
+
+fn env_url(name: &str) -> Option<String> {
+    std::env
+        ::var(name)
+        .ok()
+        .filter(|url| validate_url(url).is_ok())
+}
+
+pub struct EndpointsState {
+    path: PathBuf,
+    endpoints: RwLock<Endpoints>,
+}
+
+impl EndpointsState {
+    /// Reads the saved settings from `config_dir`, falling back to the defaults when there
+    /// are none or they are not usable.
+    pub fn load(config_dir: PathBuf) -> Self {
+        let path = config_dir.join(ENDPOINTS_FILE);
+        let endpoints = fs
+            ::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Endpoints>(&data).ok())
+            .filter(|endpoints| endpoints.validate().is_ok())
+            .unwrap_or_default()
+            .with_env_overrides();
+
+        Self {
+            path,
+            endpoints: RwLock::new(endpoints),
+        }
+    }
+
+    pub fn get(&self) -> Endpoints {
+        self.endpoints.read().map(|endpoints| endpoints.clone()).unwrap_or_default()
+    }
+
+    fn set(&self, endpoints: Endpoints) -> Result<Endpoints, LauncherError> {
+        endpoints.validate()?;
+
+        let io_error = |e| LauncherError::io(&self.path, e);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let data = serde_json::to_vec_pretty(&endpoints)?;
+        atomic_write(&self.path, &data).map_err(io_error)?;
+
+        let endpoints = endpoints.with_env_overrides();
+        if let Ok(mut current) = self.endpoints.write() {
+            *current = endpoints.clone();
+        }
+        Ok(endpoints)
+    }
+}
+
+#[command]
+pub fn get_endpoints(endpoints_state: State<'_, EndpointsState>) -> Result<Endpoints, LauncherError> {
+    Ok(endpoints_state.get())
+}
+
+#[command]
+pub async fn set_endpoints(
+    endpoints: Endpoints,
+    endpoints_state: State<'_, EndpointsState>,
+    download_manager: State<'_, DownloadManager>
+) -> Result<Endpoints, LauncherError> {
+    let endpoints = endpoints_state.set(endpoints)?;
+
+    download_manager.set_mirrors(&endpoints.manifest_mirrors, &endpoints.version_mirrors).await;
+    Ok(endpoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(Endpoints::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_empty_mirror_lists() {
+        let endpoints = Endpoints { manifest_mirrors: Vec::new(), ..Endpoints::default() };
+        assert!(endpoints.validate().is_err());
+
+        let endpoints = Endpoints { version_mirrors: Vec::new(), ..Endpoints::default() };
+        assert!(endpoints.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_urls() {
+        for url in ["", "manifest.example.com", "ftp://manifest.example.com", "http://", "file:///tmp"] {
+            let endpoints = Endpoints {
+                manifest_mirrors: vec!["https://manifest.example.com".into(), url.into()],
+                ..Endpoints::default()
+            };
+            assert!(endpoints.validate().is_err(), "{:?} was accepted", url);
+        }
+
+        let endpoints = Endpoints { ip_lookup_url: "not a url".into(), ..Endpoints::default() };
+        assert!(endpoints.validate().is_err());
+    }
+
+    #[test]
+    fn unusable_saved_settings_fall_back_to_the_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let saved = Endpoints { version_mirrors: vec!["not a url".into()], ..Endpoints::default() };
+        fs::write(dir.path().join(ENDPOINTS_FILE), serde_json::to_vec(&saved).unwrap()).unwrap();
+
+        let state = EndpointsState::load(dir.path().to_path_buf());
+        assert_eq!(state.get().version_mirrors, Endpoints::default().version_mirrors);
+    }
+
+    #[test]
+    fn only_valid_settings_are_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = EndpointsState::load(dir.path().to_path_buf());
+
+        let invalid = Endpoints { manifest_mirrors: Vec::new(), ..Endpoints::default() };
+        assert!(matches!(state.set(invalid), Err(LauncherError::InvalidRequest { .. })));
+        assert!(!dir.path().join(ENDPOINTS_FILE).exists());
+
+        let mirrors = vec!["https://a.example.com".to_string(), "https://b.example.com".to_string()];
+        state.set(Endpoints { manifest_mirrors: mirrors.clone(), ..Endpoints::default() }).unwrap();
+        let reloaded = EndpointsState::load(dir.path().to_path_buf());
+        assert_eq!(reloaded.get().manifest_mirrors, mirrors);
+    }
+}
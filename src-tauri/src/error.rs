@@ -0,0 +1,339 @@
+use serde::{ Serialize, Serializer };
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Error returned by every command and carried by `download:failed`. It serializes to an
+/// object with a stable `code`, a readable `message` and whichever details apply, so the
+/// frontend can branch on `code` instead of matching message text.
+#[derive(Debug, Clone)]
+pub enum LauncherError {
+    /// The job was cancelled or stopped while it ran.
+    Cancelled,
+    /// The download or extraction is already running or queued.
+    AlreadyActive {
+        message: String,
+    },
+    NotFound {
+        message: String,
+    },
+    InvalidVersion {
+        version: String,
+    },
+    InvalidRequest {
+        message: String,
+    },
+    Http {
+        status: u16,
+        url: String,
+    },
+    Network {
+        message: String,
+        url: Option<String>,
+    },
+    Parse {
+        message: String,
+    },
+    DiskFull {
+        path: Option<String>,
+    },
+    Io {
+        message: String,
+        path: Option<String>,
+    },
+    /// Data was downloaded but does not match what the manifest promised.
+    Integrity {
+        message: String,
+        file: Option<String>,
+        chunk_id: Option<i32>,
+    },
+    /// A chunk could not be fetched from any mirror.
+    Chunk {
+        message: String,
+        file: Option<String>,
+        chunk_id: i32,
+        status: Option<u16>,
+    },
+    Extraction {
+        message: String,
+        path: Option<String>,
+    },
+    Preflight {
+        message: String,
+    },
+    Process {
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl LauncherError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Cancelled => "cancelled",
+            Self::AlreadyActive { .. } => "already_active",
+            Self::NotFound { .. } => "not_found",
+            Self::InvalidVersion { .. } => "invalid_version",
+            Self::InvalidRequest { .. } => "invalid_request",
+            Self::Http { .. } => "http",
+            Self::Network { .. } => "network",
+            Self::Parse { .. } => "parse",
+            Self::DiskFull { .. } => "disk_full",
+            Self::Io { .. } => "io",
+            Self::Integrity { .. } => "integrity",
+            Self::Chunk { .. } => "chunk",
+            Self::Extraction { .. } => "extraction",
+            Self::Preflight { .. } => "preflight",
+            Self::Process { .. } => "process",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::Other { message: message.into() }
+    }
+
+    /// Wraps an I/O error that happened on `path`, telling a full disk apart from other failures.
+    pub fn io(path: impl AsRef<Path>, error: io::Error) -> Self {
+        let path = Some(path.as_ref().to_string_lossy().to_string());
+        if is_disk_full(&error) {
+            Self::DiskFull { path }
+        } else {
+            Self::Io { message: error.to_string(), path }
+        }
+    }
+
+    /// Attaches the install file a chunk or integrity error belongs to.
+    pub fn with_file(mut self, name: &str) -> Self {
+        if let Self::Chunk { file, .. } | Self::Integrity { file, .. } = &mut self {
+            *file = Some(name.to_string());
+        }
+        self
+    }
+}
+
+fn is_disk_full(error: &io::Error) -> bool {
+    // ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL on Windows, ENOSPC everywhere else
+    let disk_full_codes: &[i32] = if cfg!(windows) { &[39, 112] } else { &[28] };
+
+    error.kind() == io::ErrorKind::StorageFull ||
+        error.raw_os_error().is_some_and(|code| disk_full_codes.contains(&code))
+}
+
+impl fmt::Display for LauncherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "Download cancelled"),
+            Self::InvalidVersion { version } => write!(f, "Version format is incorrect: {}", version),
+            Self::Http { status, url } => write!(f, "HTTP {} from {}", status, url),
+            Self::DiskFull { path: Some(path) } => write!(f, "Not enough disk space to write {}", path),
+            Self::DiskFull { path: None } => write!(f, "Not enough disk space"),
+            Self::Io { message, path: Some(path) } => write!(f, "{} ({})", message, path),
+            Self::Chunk { message, file: Some(file), .. } => write!(f, "{} (file {})", message, file),
+            Self::Integrity { message, file: Some(file), .. } => write!(f, "{} (file {})", message, file),
+            | Self::AlreadyActive { message }
+            | Self::NotFound { message }
+            | Self::InvalidRequest { message }
+            | Self::Network { message, .. }
+            | Self::Parse { message }
+            | Self::Io { message, .. }
+            | Self::Integrity { message, .. }
+            | Self::Chunk { message, .. }
+            | Self::Extraction { message, .. }
+            | Self::Preflight { message }
+            | Self::Process { message }
+            | Self::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for LauncherError {}
+
+#[derive(Serialize)]
+struct ErrorPayload<'a> {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chunk_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'a str>,
+}
+
+impl Serialize for LauncherError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut payload = ErrorPayload {
+            code: self.code(),
+            message: self.to_string(),
+            status: None,
+            url: None,
+            path: None,
+            file: None,
+            chunk_id: None,
+            version: None,
+        };
+
+        match self {
+            Self::InvalidVersion { version } => {
+                payload.version = Some(version);
+            }
+            Self::Http { status, url } => {
+                payload.status = Some(*status);
+                payload.url = Some(url);
+            }
+            Self::Network { url, .. } => {
+                payload.url = url.as_deref();
+            }
+            Self::DiskFull { path } | Self::Io { path, .. } | Self::Extraction { path, .. } => {
+                payload.path = path.as_deref();
+            }
+            Self::Integrity { file, chunk_id, .. } => {
+                payload.file = file.as_deref();
+                payload.chunk_id = *chunk_id;
+            }
+            Self::Chunk { file, chunk_id, status, .. } => {
+                payload.file = file.as_deref();
+                payload.chunk_id = Some(*chunk_id);
+                payload.status = *status;
+            }
+            _ => {}
+        }
+
+        payload.serialize(serializer)
+    }
+}
+
+impl From<io::Error> for LauncherError {
+    fn from(error: io::Error) -> Self {
+        if is_disk_full(&error) {
+            Self::DiskFull { path: None }
+        } else {
+            Self::Io { message: error.to_string(), path: None }
+        }
+    }
+}
+
+impl From<reqwest::Error> for LauncherError {
+    fn from(error: reqwest::Error) -> Self {
+        let url = error.url().map(|url| url.to_string());
+        match error.status() {
+            Some(status) =>
+                Self::Http {
+                    status: status.as_u16(),
+                    url: url.unwrap_or_default(),
+                },
+            None if error.is_decode() => Self::Parse { message: error.to_string() },
+            None => Self::Network { message: error.to_string(), url },
+        }
+    }
+}
+
+impl From<serde_json::Error> for LauncherError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Parse { message: error.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let error = LauncherError::Http { status: 503, url: "https://cdn.example.com/a".into() };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "http",
+                "message": "HTTP 503 from https://cdn.example.com/a",
+                "status": 503,
+                "url": "https://cdn.example.com/a",
+            })
+        );
+
+        let error = LauncherError::Chunk {
+            message: "Chunk 7 was never delivered".into(),
+            file: Some("Game/a.pak".into()),
+            chunk_id: 7,
+            status: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "chunk",
+                "message": "Chunk 7 was never delivered (file Game/a.pak)",
+                "file": "Game/a.pak",
+                "chunk_id": 7,
+            })
+        );
+    }
+
+    #[test]
+    fn leaves_out_details_that_do_not_apply() {
+        assert_eq!(serde_json::to_value(LauncherError::Cancelled).unwrap(), json!({
+            "code": "cancelled",
+            "message": "Download cancelled",
+        }));
+        assert_eq!(serde_json::to_value(LauncherError::DiskFull { path: None }).unwrap(), json!({
+            "code": "disk_full",
+            "message": "Not enough disk space",
+        }));
+        assert_eq!(serde_json::to_value(LauncherError::InvalidVersion { version: "x".into() }).unwrap(), json!({
+            "code": "invalid_version",
+            "message": "Version format is incorrect: x",
+            "version": "x",
+        }));
+    }
+
+    #[test]
+    fn codes_are_stable() {
+        let codes = [
+            (LauncherError::AlreadyActive { message: String::new() }, "already_active"),
+            (LauncherError::NotFound { message: String::new() }, "not_found"),
+            (LauncherError::InvalidRequest { message: String::new() }, "invalid_request"),
+            (LauncherError::Network { message: String::new(), url: None }, "network"),
+            (LauncherError::Parse { message: String::new() }, "parse"),
+            (LauncherError::Io { message: String::new(), path: None }, "io"),
+            (LauncherError::Integrity { message: String::new(), file: None, chunk_id: None }, "integrity"),
+            (LauncherError::Extraction { message: String::new(), path: None }, "extraction"),
+            (LauncherError::Preflight { message: String::new() }, "preflight"),
+            (LauncherError::Process { message: String::new() }, "process"),
+            (LauncherError::other(""), "other"),
+        ];
+        for (error, code) in codes {
+            assert_eq!(error.code(), code);
+            assert_eq!(serde_json::to_value(&error).unwrap()["code"], code);
+        }
+    }
+
+    #[test]
+    fn tells_a_full_disk_apart() {
+        let full = io::Error::new(io::ErrorKind::StorageFull, "no space left");
+        assert!(matches!(
+            LauncherError::io("/games/a.pak", full),
+            LauncherError::DiskFull { path: Some(path) } if path == "/games/a.pak"
+        ));
+
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        assert!(matches!(LauncherError::io("/games/a.pak", denied), LauncherError::Io { .. }));
+    }
+
+    #[test]
+    fn attaches_the_file_to_chunk_and_integrity_errors() {
+        let error = LauncherError::Integrity { message: "Hash mismatch".into(), file: None, chunk_id: Some(3) };
+        assert!(matches!(error.with_file("a.pak"), LauncherError::Integrity { file: Some(file), .. } if file == "a.pak"));
+
+        let error = LauncherError::Parse { message: "bad".into() }.with_file("a.pak");
+        assert_eq!(serde_json::to_value(&error).unwrap(), json!({ "code": "parse", "message": "bad" }));
+    }
+}
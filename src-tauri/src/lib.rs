@@ -1,21 +1,17 @@
 use declarative_discord_rich_presence::DeclarativeDiscordIpcClient;
 use declarative_discord_rich_presence::activity::{ Activity, Assets, Button, Timestamps };
-use futures_util::StreamExt;
 use regex::Regex;
-use reqwest::StatusCode;
 use std::ffi::CString;
-use std::fs::{ self, File, OpenOptions };
+use std::fs::{ self, File };
 use std::io::Read;
 use std::io::Write;
 use std::os::windows::process::CommandExt;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
-use std::time::{ Duration, Instant };
 use sysinfo::{ System, SystemExt };
-use tauri::AppHandle;
-use tauri::Emitter;
 use tauri::Manager;
+use tauri::State;
 use tauri::WindowEvent;
 use winapi::um::winbase::CREATE_SUSPENDED;
 use windows::Win32::Foundation::HWND;
@@ -24,25 +20,50 @@ use windows::Win32::UI::WindowsAndMessaging::{ SW_HIDE, SW_SHOW };
 use windows::core::PCSTR;
 
 mod builds;
+mod endpoints;
+mod error;
 use builds::download_manager::{
     DownloadManager,
     cancel_download,
     cancel_extraction,
+    clear_chunk_cache,
     download_build,
+    download_game_file,
+    export_download_history,
     get_available_versions,
+    get_bandwidth_limits,
+    get_cdn_mirrors,
+    get_chunk_cache_info,
     get_default_install_dir,
+    get_download_history,
+    get_download_queue,
+    get_downloads,
+    get_download_status,
     get_manifest_for_version,
+    get_unfinished_downloads,
     is_download_active,
     is_extraction_active,
+    move_queued_download,
+    pause_download,
+    preflight_install,
+    prune_chunk_cache,
+    repair_build,
+    resume_download,
+    set_bandwidth_limit,
+    set_chunk_cache_quota,
+    set_download_bandwidth_limit,
+    set_download_priority,
+    set_max_concurrent_downloads,
+    update_build,
+    verify_build,
 };
+use endpoints::{ EndpointsState, get_endpoints, set_endpoints };
+use error::LauncherError;
 
 const CREATE_NO_WINDOW: u32 = 0x08000000;
-const MAX_RETRIES: usize = 300;
-const TIMEOUT_SECONDS: u64 = 300;
-const MIN_PROGRESS_INTERVAL_MS: u64 = 500;
 
 #[tauri::command]
-fn get_fortnite_processid() -> Result<Option<String>, String> {
+fn get_fortnite_processid() -> Result<Option<String>, LauncherError> {
     let output = std::process::Command
         ::new("wmic")
         .creation_flags(CREATE_NO_WINDOW)
@@ -56,7 +77,7 @@ fn get_fortnite_processid() -> Result<Option<String>, String> {
             ]
         )
         .output()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| LauncherError::Process { message: e.to_string() })?;
 
     let output_str = String::from_utf8_lossy(&output.stdout);
 
@@ -71,7 +92,7 @@ fn get_fortnite_processid() -> Result<Option<String>, String> {
 }
 
 #[tauri::command]
-fn exit_all() -> Result<(), String> {
+fn exit_all() -> Result<(), LauncherError> {
     use std::env;
     use std::fs::File;
     use std::io::Write;
@@ -92,22 +113,23 @@ fn exit_all() -> Result<(), String> {
     let temp_dir = env::temp_dir();
     let batch_path = temp_dir.join("close.bat");
 
-    let mut batch_file = File::create(&batch_path).map_err(|e|
-        format!("Failed to create batch file: {}", e)
-    )?;
+    let batch_error = |e| LauncherError::io(&batch_path, e);
+    let mut batch_file = File::create(&batch_path).map_err(batch_error)?;
 
-    writeln!(batch_file, "@echo off").map_err(|e| format!("Write error: {}", e))?;
+    writeln!(batch_file, "@echo off").map_err(batch_error)?;
     for process in processes {
-        writeln!(batch_file, "taskkill /F /IM \"{}\" >nul 2>&1", process).map_err(|e|
-            format!("Write error: {}", e)
-        )?;
+        writeln!(batch_file, "taskkill /F /IM \"{}\" >nul 2>&1", process).map_err(batch_error)?;
     }
-    writeln!(batch_file, "del \"%~f0\"").map_err(|e| format!("Write error: {}", e))?;
+    writeln!(batch_file, "del \"%~f0\"").map_err(batch_error)?;
 
     drop(batch_file);
 
-    let batch_path_str = batch_path.to_str().ok_or("Invalid path")?;
-    let batch_cstring = CString::new(batch_path_str).map_err(|e| format!("CString error: {}", e))?;
+    let batch_path_str = batch_path.to_str().ok_or_else(|| LauncherError::InvalidRequest {
+        message: "Invalid path".into(),
+    })?;
+    let batch_cstring = CString::new(batch_path_str).map_err(|e| LauncherError::Process {
+        message: format!("CString error: {}", e),
+    })?;
 
     let result = unsafe {
         ShellExecuteA(
@@ -121,14 +143,14 @@ fn exit_all() -> Result<(), String> {
     };
 
     if result.is_invalid() {
-        return Err("Failed to close game with batch file".to_string());
+        return Err(LauncherError::Process { message: "Failed to close game with batch file".into() });
     }
 
     Ok(())
 }
 
 #[tauri::command]
-async fn check_file_exists_and_size(path: &str, size: Option<u64>) -> Result<bool, String> {
+async fn check_file_exists_and_size(path: &str, size: Option<u64>) -> Result<bool, LauncherError> {
     let file_path = std::path::PathBuf::from(path);
     if !file_path.exists() {
         return Ok(false);
@@ -139,7 +161,7 @@ async fn check_file_exists_and_size(path: &str, size: Option<u64>) -> Result<boo
             let actual_size = match file_path.metadata() {
                 Ok(metadata) => metadata.len(),
                 Err(err) => {
-                    return Err(err.to_string());
+                    return Err(LauncherError::io(&file_path, err));
                 }
             };
 
@@ -150,7 +172,7 @@ async fn check_file_exists_and_size(path: &str, size: Option<u64>) -> Result<boo
 }
 
 #[tauri::command]
-async fn check_file_exists(path: &str) -> Result<bool, String> {
+async fn check_file_exists(path: &str) -> Result<bool, LauncherError> {
     let file_path = std::path::PathBuf::from(path);
 
     if !file_path.exists() {
@@ -161,10 +183,10 @@ async fn check_file_exists(path: &str) -> Result<bool, String> {
 }
 
 #[tauri::command]
-fn search_for_version(path: &str) -> Result<Vec<String>, String> {
-    let mut file = File::open(path).map_err(|e| e.to_string())?;
+fn search_for_version(path: &str) -> Result<Vec<String>, LauncherError> {
+    let mut file = File::open(path).map_err(|e| LauncherError::io(path, e))?;
     let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
+    file.read_to_end(&mut buffer).map_err(|e| LauncherError::io(path, e))?;
 
     let pattern = [
         0x2b, 0x00, 0x2b, 0x00, 0x46, 0x00, 0x6f, 0x00, 0x72, 0x00, 0x74, 0x00, 0x6e, 0x00, 0x69, 0x00,
@@ -273,15 +295,9 @@ fn download_file(url: &str, dest: &Path) -> Result<(), Box<dyn std::error::Error
 }
 
 #[tauri::command]
-fn get_user_ip() -> Result<String, String> {
-    match reqwest::blocking::get("https://api4.ipify.org") {
-        Ok(response) =>
-            match response.text() {
-                Ok(ip) => Ok(ip),
-                Err(e) => Err(format!("Failed to parse response: {}", e)),
-            }
-        Err(e) => Err(format!("Failed to make request: {}", e)),
-    }
+fn get_user_ip(endpoints_state: State<'_, EndpointsState>) -> Result<String, LauncherError> {
+    let response = reqwest::blocking::get(endpoints_state.get().ip_lookup_url)?;
+    Ok(response.text()?)
 }
 
 #[tauri::command]
@@ -293,8 +309,9 @@ fn experience(
     dpe: bool,
     ror: bool,
     a: String,
-    version: String
-) -> Result<bool, String> {
+    version: String,
+    endpoints_state: State<'_, EndpointsState>
+) -> Result<bool, LauncherError> {
     std::thread::sleep(std::time::Duration::from_secs(2));
     let game_path = PathBuf::from(folder_path);
 
@@ -329,7 +346,7 @@ fn experience(
         //     &game_dll
         // );
 
-        let _ = download_file("https://cdn.asteria-ac.xyz/Asteria.vmp.dll", &game_dll);
+        let _ = download_file(&endpoints_state.get().asteria_dll_url, &game_dll);
 
         let dlls = [
             "api-ms-win-core-errorhandling-l1-1-0.dll",
@@ -434,12 +451,15 @@ fn experience(
     }
 
     let hwnd: HWND = HWND(std::ptr::null_mut());
-    let args_cstring = CString::new(fort_args.join(" ")).map_err(|e|
-        format!("CString error: {}", e)
-    )?;
+    let cstring_error = |e: std::ffi::NulError| LauncherError::Process {
+        message: format!("CString error: {}", e),
+    };
+    let args_cstring = CString::new(fort_args.join(" ")).map_err(cstring_error)?;
 
-    let exe_str = game_real.to_str().ok_or("Invalid path")?;
-    let exe_cstring = CString::new(exe_str).map_err(|e| format!("CString error: {}", e))?;
+    let exe_str = game_real.to_str().ok_or_else(|| LauncherError::InvalidRequest {
+        message: "Invalid path".into(),
+    })?;
+    let exe_cstring = CString::new(exe_str).map_err(cstring_error)?;
 
     let result = unsafe {
         ShellExecuteA(
@@ -453,7 +473,7 @@ fn experience(
     };
 
     if result.is_invalid() {
-        return Err("Failed to start Solaris".to_string());
+        return Err(LauncherError::Process { message: "Failed to start Solaris".into() });
     }
 
     std::thread::sleep(std::time::Duration::from_secs(5));
@@ -500,239 +520,31 @@ fn rich_presence(username: String, character: String) {
 }
 
 #[tauri::command]
-async fn check_game_exists(path: &str) -> Result<bool, String> {
+async fn check_game_exists(path: &str) -> Result<bool, LauncherError> {
     let game_path = PathBuf::from(path);
     let mut game = game_path.clone();
     game.push("FortniteGame\\Binaries\\Win64\\FortniteClient-Win64-Shipping.exe");
 
     if !game.exists() {
-        return Err("Hmmm could not find all Fortnite files".to_string());
+        return Err(LauncherError::NotFound { message: "Hmmm could not find all Fortnite files".into() });
     } else {
         Ok(true)
     }
 }
 
 #[tauri::command]
-async fn download_game_file(url: &str, dest: &str, app: AppHandle) -> Result<(), String> {
-    let dest_path = Path::new(dest);
-
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directories: {}", e))?;
-    }
-
-    let filename = dest_path
-        .file_name()
-        .and_then(|f| f.to_str())
-        .unwrap_or("unknown");
-
-    let mut downloaded: u64 = 0;
-    let mut retry_count: usize = 0;
-    let mut file_size: u64 = 0;
-
-    if dest_path.exists() {
-        match fs::remove_file(dest_path) {
-            Ok(_) => {
-                downloaded = 0;
-
-                let _ = app.emit(
-                    "download-progress",
-                    serde_json::json!({
-                        "filename": filename,
-                        "downloaded": downloaded,
-                        "total": 0,
-                        "progress": 0,
-                        "speed": 0.0,
-                        "message": "Old file deleted, starting fresh download..."
-                    })
-                );
-            }
-            Err(e) => {
-                return Err(format!("Failed to delete old file: {}", e));
-            }
-        }
-    }
-
-    let mut last_update_time = Instant::now();
-    let mut bytes_since_last_update: u64 = 0;
-    let mut last_progress_percentage: u64 = 0;
-
-    let client = reqwest::Client
-        ::builder()
-        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
-        .connect_timeout(Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
-    while retry_count < MAX_RETRIES {
-        if retry_count > 0 {
-            let _ = app.emit(
-                "download-progress",
-                serde_json::json!({
-                    "filename": filename,
-                    "downloaded": downloaded,
-                    "total": file_size,
-                    "progress": if file_size > 0 { (downloaded * 100) / file_size } else { 0 },
-                    "speed": 0.0,
-                    "message": format!("Retry attempt {} of {}", retry_count, MAX_RETRIES)
-                })
-            );
-        }
-
-        let mut file = if downloaded > 0 {
-            match OpenOptions::new().write(true).append(true).open(dest_path) {
-                Ok(f) => f,
-                Err(_e) => {
-                    downloaded = 0;
-                    File::create(dest_path).map_err(|e| format!("Failed to create file: {}", e))?
-                }
-            }
-        } else {
-            File::create(dest_path).map_err(|e| format!("Failed to create file: {}", e))?
-        };
-
-        let mut request = client.get(url);
-        if downloaded > 0 {
-            request = request.header("Range", format!("bytes={}-", downloaded));
-        }
-
-        match request.send().await {
-            Ok(response) => {
-                let status = response.status();
-
-                if !(status.is_success() || status == StatusCode::PARTIAL_CONTENT) {
-                    retry_count += 1;
-                    continue;
-                }
-
-                if file_size == 0 {
-                    file_size = if status == StatusCode::PARTIAL_CONTENT {
-                        if let Some(content_range) = response.headers().get("content-range") {
-                            if let Ok(range_str) = content_range.to_str() {
-                                if let Some(size_str) = range_str.split('/').nth(1) {
-                                    size_str.parse::<u64>().unwrap_or(0)
-                                } else {
-                                    0
-                                }
-                            } else {
-                                0
-                            }
-                        } else {
-                            response.content_length().unwrap_or(0) + downloaded
-                        }
-                    } else {
-                        response.content_length().unwrap_or(0)
-                    };
-                }
-
-                let mut stream = response.bytes_stream();
-                let mut chunk_timeout = false;
-
-                while let Some(chunk_result) = stream.next().await {
-                    match chunk_result {
-                        Ok(chunk) => {
-                            chunk_timeout = false;
-
-                            match file.write_all(&chunk) {
-                                Ok(_) => {
-                                    downloaded += chunk.len() as u64;
-                                    bytes_since_last_update += chunk.len() as u64;
-
-                                    let progress = if file_size > 0 {
-                                        (downloaded * 100) / file_size
-                                    } else {
-                                        0
-                                    };
-
-                                    let time_since_update = last_update_time.elapsed();
-                                    if
-                                        progress > last_progress_percentage ||
-                                        time_since_update.as_millis() >
-                                            (MIN_PROGRESS_INTERVAL_MS as u128)
-                                    {
-                                        let speed_mbps = if time_since_update.as_secs_f64() > 0.0 {
-                                            (bytes_since_last_update as f64) /
-                                                (1024.0 * 1024.0) /
-                                                time_since_update.as_secs_f64()
-                                        } else {
-                                            0.0
-                                        };
-
-                                        let _ = app.emit(
-                                            "download-progress",
-                                            serde_json::json!({
-                                                "filename": filename,
-                                                "downloaded": downloaded,
-                                                "total": file_size,
-                                                "progress": progress,
-                                                "speed": speed_mbps,
-                                                "message": "Downloading..."
-                                            })
-                                        );
-
-                                        last_progress_percentage = progress;
-                                        last_update_time = Instant::now();
-                                        bytes_since_last_update = 0;
-                                    }
-                                }
-                                Err(e) => {
-                                    return Err(format!("Failed to write to file: {}", e));
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            if e.is_timeout() {
-                                chunk_timeout = true;
-                                break;
-                            }
-                            return Err(format!("Error downloading: {}", e));
-                        }
-                    }
-                }
-
-                if !chunk_timeout {
-                    if file_size == 0 || downloaded >= file_size {
-                        let _ = app.emit(
-                            "download-completed",
-                            serde_json::json!({
-                                "filename": filename,
-                                "size": downloaded
-                            })
-                        );
-                        return Ok(());
-                    }
-                }
-
-                retry_count += 1;
-            }
-            Err(e) => {
-                if e.is_timeout() {
-                    retry_count += 1;
-                    continue;
-                }
-                return Err(format!("Request error: {}", e));
-            }
-        }
-    }
-
-    Err(format!("Failed to download after {} retries", MAX_RETRIES))
-}
-
-#[tauri::command]
-fn delete_file(file_path: String) -> Result<(), String> {
+fn delete_file(file_path: String) -> Result<(), LauncherError> {
     let path = Path::new(&file_path);
 
     if !path.exists() {
-        return Err(format!("File does not exist: {}", file_path));
+        return Err(LauncherError::NotFound { message: format!("File does not exist: {}", file_path) });
     }
 
     if !path.is_file() {
-        return Err(format!("{} is not a file", file_path));
+        return Err(LauncherError::InvalidRequest { message: format!("{} is not a file", file_path) });
     }
 
-    match fs::remove_file(path) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Failed to delete file: {}", e)),
-    }
+    fs::remove_file(path).map_err(|e| LauncherError::io(path, e))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -748,7 +560,6 @@ pub fn run() {
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
-        .manage(DownloadManager::new())
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app
@@ -758,6 +569,16 @@ pub fn run() {
                     )?;
             }
 
+            let config_dir = app.path().app_config_dir()?;
+            let endpoints_state = EndpointsState::load(config_dir);
+            app.manage(DownloadManager::with_endpoints(&endpoints_state.get()));
+            app.manage(endpoints_state);
+
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                app_handle.state::<DownloadManager>().probe_mirrors().await;
+            });
+
             let window = app.get_webview_window("main").unwrap();
 
             window.on_window_event(|event| {
@@ -799,15 +620,40 @@ pub fn run() {
                 experience,
                 download_game_file,
                 download_build,
+                preflight_install,
                 is_download_active,
                 is_extraction_active,
                 cancel_download,
                 cancel_extraction,
+                pause_download,
+                get_download_status,
+                get_download_queue,
+                get_downloads,
+                move_queued_download,
+                set_download_priority,
+                set_max_concurrent_downloads,
+                get_bandwidth_limits,
+                set_bandwidth_limit,
+                set_download_bandwidth_limit,
                 delete_file,
                 get_default_install_dir,
                 get_available_versions,
+                get_cdn_mirrors,
+                get_chunk_cache_info,
+                set_chunk_cache_quota,
+                prune_chunk_cache,
+                clear_chunk_cache,
                 get_manifest_for_version,
-                get_user_ip
+                get_unfinished_downloads,
+                update_build,
+                verify_build,
+                repair_build,
+                resume_download,
+                get_download_history,
+                export_download_history,
+                get_user_ip,
+                get_endpoints,
+                set_endpoints
             ]
         )
         .run(tauri::generate_context!())
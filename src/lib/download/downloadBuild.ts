@@ -41,6 +41,36 @@ interface TauriDownloadProgress {
   eta: string;
 }
 
+interface TauriCached<T> {
+  data: T;
+  stale: boolean;
+  fetched_at: number;
+}
+
+export interface LauncherError {
+  code: string;
+  message: string;
+  status?: number;
+  url?: string;
+  path?: string;
+  file?: string;
+  chunk_id?: number;
+  version?: string;
+}
+
+interface TauriDownloadFailed {
+  build_id: string;
+  error: LauncherError;
+}
+
+export function errorMessage(error: unknown): string {
+  if (error instanceof Error) return error.message;
+  if (typeof error === "object" && error !== null && "message" in error) {
+    return String((error as LauncherError).message);
+  }
+  return String(error);
+}
+
 function formatBytes(bytes: number): string {
   const units = ["B", "KB", "MB", "GB", "TB"];
   let size = bytes;
@@ -98,7 +128,8 @@ export async function selectInstallDirectory(defaultPath?: string): Promise<stri
 
 export async function getAvailableVersions(): Promise<string[]> {
   try {
-    return await invoke<string[]>("get_available_versions");
+    const versions = await invoke<TauriCached<string[]>>("get_available_versions");
+    return versions.data;
   } catch (error) {
     console.error("Error fetching available versions:", error);
     return [];
@@ -107,7 +138,8 @@ export async function getAvailableVersions(): Promise<string[]> {
 
 export async function getManifestForVersion(version: string): Promise<ManifestFile> {
   try {
-    return await invoke<ManifestFile>("get_manifest_for_version", { version });
+    const manifest = await invoke<TauriCached<ManifestFile>>("get_manifest_for_version", { version });
+    return manifest.data;
   } catch (error) {
     console.error("Error fetching manifest:", error);
     throw error;
@@ -162,12 +194,12 @@ export async function downloadBuild(
       }
     });
 
-    const unlistenDownloadError = await listen<string>("download:failed", (event) => {
-      if (event.payload === buildId) {
+    const unlistenDownloadError = await listen<TauriDownloadFailed>("download:failed", (event) => {
+      if (event.payload.build_id === buildId) {
         unlistenDownloadProgress();
         unlistenDownloadComplete();
         unlistenDownloadError();
-        if (onError) onError("Download failed");
+        if (onError) onError(event.payload.error.message || "Download failed");
       }
     });
 
@@ -188,13 +220,13 @@ export async function downloadBuild(
       unlistenDownloadComplete();
       unlistenDownloadError();
 
-      if (onError) onError(error instanceof Error ? error.message : String(error));
+      if (onError) onError(errorMessage(error));
       return false;
     }
 
     return true;
   } catch (error) {
-    if (onError) onError(error instanceof Error ? error.message : String(error));
+    if (onError) onError(errorMessage(error));
     return false;
   }
 }
@@ -208,6 +240,25 @@ export async function cancelDownload(buildId: string): Promise<boolean> {
   }
 }
 
+export async function pauseDownload(buildId: string): Promise<boolean> {
+  try {
+    return await invoke<boolean>("pause_download", { buildId });
+  } catch (error) {
+    console.error("Error pausing download:", error);
+    return false;
+  }
+}
+
+export async function resumeDownload(buildId: string): Promise<boolean> {
+  try {
+    await invoke("resume_download", { buildId });
+    return true;
+  } catch (error) {
+    console.error("Error resuming download:", error);
+    return false;
+  }
+}
+
 export async function cancelExtraction(buildId: string): Promise<boolean> {
   try {
     return await invoke<boolean>("cancel_extraction", { buildId });
@@ -7,6 +7,7 @@ import { createExchangeCode } from "@/api/authentication/requests/verify";
 import { generateFilesResponse } from "@/api/main/requests/files";
 import { listen } from "@tauri-apps/api/event";
 import { generateAsteriaToken } from "@/api/authentication/requests/asteria";
+import { errorMessage } from "@/lib/download/downloadBuild";
 
 const appWindow = getCurrentWebviewWindow();
 
@@ -100,22 +101,28 @@ export async function processFilesWithProgress(
 
   setIsDownloadModalOpen(true);
 
-  const unlistenProgress = await listen("download-progress", (event) => {
-    const { filename, progress, speed, message } = event.payload as {
-      filename: string;
-      progress: number;
+  // Each file is downloaded as its own job, keyed by its destination path
+  const jobFiles: Record<string, string> = {};
+
+  const unlistenProgress = await listen("download:progress", (event) => {
+    const { build_id, percentage, speed, eta } = event.payload as {
+      build_id: string;
+      percentage: number;
       speed: number;
-      message?: string;
+      eta: string;
     };
 
-    downloadSpeeds[filename] = speed;
-    downloadProgress[filename] = progress;
+    const filename = jobFiles[build_id];
+    if (!filename) return;
 
-    if (message) {
-      statusMessages[filename] = message;
-    }
+    const progress = Math.floor(percentage);
+    const speedMbps = speed / (1024 * 1024);
 
-    console.log(`Downloading ${filename}: ${progress}% (${speed.toFixed(2)} MB/s)`);
+    downloadSpeeds[filename] = speedMbps;
+    downloadProgress[filename] = progress;
+    statusMessages[filename] = `Downloading... ${eta} remaining`;
+
+    console.log(`Downloading ${filename}: ${progress}% (${speedMbps.toFixed(2)} MB/s)`);
 
     setDownloadProgress((prev: any) => ({
       ...prev,
@@ -125,8 +132,9 @@ export async function processFilesWithProgress(
     }));
   });
 
-  const unlistenCompleted = await listen("download-completed", (event) => {
-    const { filename } = event.payload as { filename: string };
+  const unlistenCompleted = await listen("download:completed", (event) => {
+    const filename = jobFiles[event.payload as string];
+    if (!filename) return;
     console.log(`Download completed for ${filename}`);
 
     if (!completedFiles.includes(filename)) {
@@ -171,9 +179,12 @@ export async function processFilesWithProgress(
           messages: { ...statusMessages },
         }));
 
+        const dest = `${downloadPath}${file.Name}`;
+        jobFiles[dest] = file.Name;
+
         await invoke("download_game_file", {
           url: file.Url,
-          dest: `${downloadPath}${file.Name}`,
+          dest,
         });
       } else {
         if (!completedFiles.includes(file.Name)) {
@@ -188,9 +199,10 @@ export async function processFilesWithProgress(
       }
     } catch (error) {
       console.error(`Error processing file ${file.Name}:`, error);
-      statusMessages[file.Name] = `Error: ${error}`;
+      const message = errorMessage(error);
+      statusMessages[file.Name] = `Error: ${message}`;
 
-      failed.push({ file, error: String(error) });
+      failed.push({ file, error: message });
 
       if (!completedFiles.includes(file.Name)) {
         completedFiles.push(file.Name);